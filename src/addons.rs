@@ -0,0 +1,108 @@
+use std::{collections::HashMap, path::PathBuf};
+
+use dialoguer::{theme::ColorfulTheme, MultiSelect};
+
+use crate::{
+    config::PackageScripts,
+    error::{Context, Error, Result},
+    module::{Module, ThenCommands},
+};
+
+/// An optional feature declared under `[addons.<name>]` in a stack's manifest, which the user
+/// picks from a list
+#[derive(Debug, Clone)]
+pub struct Addon {
+    pub name: String,
+    pub label: String,
+    pub description: String,
+    pub npm_deps: Vec<Module>,
+    pub cargo_deps: Vec<Module>,
+    /// Scripts per package manager, added to the stack's
+    pub scripts: HashMap<String, PackageScripts>,
+    /// Folder of the stack copied into the project after the after_install files
+    pub files: PathBuf,
+    /// Commands run in the project once the addon's files are copied
+    pub then: ThenCommands,
+}
+
+impl Addon {
+    /// Label shown in the addon list, followed by the description when there is one
+    pub fn get_item(&self) -> String {
+        match self.description.is_empty() {
+            true => self.label.clone(),
+            false => format!("{} - {}", self.label, self.description),
+        }
+    }
+}
+
+/// Picks the addons to install: the ones supplied on the command line or in an answers file,
+/// otherwise asks the user. Without prompts no addon is installed.
+pub fn select(
+    addons: &[Addon],
+    supplied: Option<&[String]>,
+    interactive: bool,
+) -> Result<Vec<String>> {
+    match supplied {
+        Some(names) => {
+            for name in names {
+                if !addons.iter().any(|addon| &addon.name == name) {
+                    return Err(unknown_addon(addons, name));
+                }
+            }
+            Ok(addons
+                .iter()
+                .filter(|addon| names.contains(&addon.name))
+                .map(|addon| addon.name.clone())
+                .collect())
+        }
+        None if interactive && !addons.is_empty() => {
+            let items = addons.iter().map(Addon::get_item).collect::<Vec<_>>();
+            let selected = MultiSelect::with_theme(&ColorfulTheme::default())
+                .with_prompt("Which addons would you like to install? (space to select)")
+                .items(&items)
+                .interact()
+                .context("Failed to get addon selection from user")?;
+            Ok(selected
+                .into_iter()
+                .map(|index| addons[index].name.clone())
+                .collect())
+        }
+        None => Ok(vec![]),
+    }
+}
+
+fn unknown_addon(addons: &[Addon], name: &str) -> Error {
+    let names = addons
+        .iter()
+        .map(|addon| addon.name.as_str())
+        .collect::<Vec<_>>();
+    match names.is_empty() {
+        true => Error::Config(format!(
+            "Unknown addon `{}`, this stack has no addons",
+            name
+        )),
+        false => Error::Config(format!(
+            "Unknown addon `{}`, this stack's addons are: {}",
+            name,
+            names.join(", ")
+        )),
+    }
+}
+
+/// Adds an addon's deps to the stack's, replacing the stack's entry for a package it also lists
+/// under the same condition
+pub fn merge_deps(deps: &mut Option<Vec<Module>>, addon_deps: &[Module]) {
+    if addon_deps.is_empty() {
+        return;
+    }
+    let deps = deps.get_or_insert_with(Vec::new);
+    for module in addon_deps {
+        match deps
+            .iter_mut()
+            .find(|dep| dep.name == module.name && dep.when == module.when)
+        {
+            Some(dep) => *dep = module.clone(),
+            None => deps.push(module.clone()),
+        }
+    }
+}
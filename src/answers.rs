@@ -0,0 +1,128 @@
+use std::{fs, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    cli::NewArgs,
+    dialogue::{Database, UserOptions},
+    error::{Context, Error, Result},
+    expression::Variables,
+};
+
+/// File name of the answers record written to the root of every new project
+pub const ANSWERS_FILE: &str = ".germinate-answers.toml";
+
+/// Answers collected by the dialogue, in a form that can be written to disk and replayed with
+/// `--answers <file>`. Missing keys fall back to their defaults on replay.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Answers {
+    pub stack: Option<String>,
+    pub name: Option<String>,
+    pub db: Option<Database>,
+    pub orm: Option<bool>,
+    pub spa: Option<bool>,
+    pub template_engine: Option<bool>,
+    pub containers: Option<bool>,
+    pub addons: Option<Vec<String>>,
+    /// Answers to the prompts declared by the stack
+    #[serde(default, skip_serializing_if = "Variables::is_empty")]
+    pub prompts: Variables,
+}
+
+impl Answers {
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("Error reading answers file {}", path.display()))?;
+        toml::from_str(&contents).map_err(|err| {
+            Error::Config(format!(
+                "Error parsing answers file {}: {}",
+                path.display(),
+                err
+            ))
+        })
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let contents = toml::to_string(self)
+            .map_err(|err| Error::Config(format!("Error serializing answers: {}", err)))?;
+        fs::write(path, contents)
+            .with_context(|| format!("Error writing answers file {}", path.display()))
+    }
+
+    /// Fills every option not supplied on the command line from the answers file. Replaying a
+    /// file never prompts, so unanswered options take their defaults.
+    pub fn apply_to(self, args: &mut NewArgs) {
+        args.stack = args.stack.take().or(self.stack);
+        args.name = args.name.take().or(self.name);
+        if !args.no_db {
+            args.db = args.db.take().or(self.db);
+        }
+        args.orm = args.orm.or(self.orm);
+        args.spa = args.spa.or(self.spa);
+        args.template_engine = args.template_engine.or(self.template_engine);
+        args.containers = args.containers.or(self.containers);
+        args.addons = args.addons.take().or(self.addons);
+        args.prompt_answers = self.prompts;
+        args.no_input = true;
+    }
+}
+
+impl From<&UserOptions> for Answers {
+    fn from(options: &UserOptions) -> Self {
+        Self {
+            stack: Some(options.stack.name.clone()),
+            name: Some(options.app_name.clone()),
+            db: options.db.clone(),
+            orm: Some(options.orm),
+            spa: Some(options.spa),
+            template_engine: Some(options.template_engine),
+            containers: Some(options.containers),
+            addons: Some(options.addons.clone()),
+            prompts: options.prompt_answers.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replay_answers_under_cli_flags() {
+        let answers: Answers = toml::from_str(
+            r#"
+            stack = "rsapi"
+            name = "billing"
+            db = "postgres"
+            orm = true
+            spa = false
+            "#,
+        )
+        .expect("Error parsing answers");
+
+        let mut args = NewArgs {
+            name: Some("invoicing".to_string()),
+            ..Default::default()
+        };
+        answers.apply_to(&mut args);
+
+        assert_eq!(args.stack.as_deref(), Some("rsapi"));
+        assert_eq!(args.name.as_deref(), Some("invoicing"));
+        assert!(matches!(args.db, Some(Database::Postgres)));
+        assert_eq!(args.orm, Some(true));
+        assert_eq!(args.containers, None);
+        assert!(args.no_input);
+    }
+
+    #[test]
+    fn reject_unknown_answers() {
+        let error =
+            toml::from_str::<Answers>("stack = \"rsapi\"\ncontianers = true\n").unwrap_err();
+        assert!(
+            error.message().starts_with("unknown field `contianers`"),
+            "{}",
+            error
+        );
+    }
+}
@@ -1,120 +1,451 @@
 use crate::{
-    config::ScaffoldConfig, container::ContainerBuilder, dialogue::StackTemplate, file_system,
-    linters::Linter, module,
+    answers::{Answers, ANSWERS_FILE},
+    config::{Language, PackageManager, ScaffoldConfig},
+    container::{self, ContainerBuilder},
+    error::{Context, Error, Result},
+    file_system,
+    linters::Linter,
+    module,
+};
+use slug::slugify;
+use std::{
+    collections::{HashMap, HashSet},
+    env,
+    path::{Path, PathBuf},
+    process::{Command, Output},
+    time::{SystemTime, UNIX_EPOCH},
+    vec,
 };
-use std::{collections::HashMap, env, io::Result, path::PathBuf, process::Command, vec};
 
 pub struct ProjectBuilder {}
 
+#[derive(Debug, Clone, Default)]
+pub struct BuildOptions {
+    /// Move a partially built project aside instead of removing it when a step fails
+    pub keep_on_failure: bool,
+    /// What to do when the project folder already exists and is not empty
+    pub existing: ExistingDir,
+}
+
+/// How to treat a project folder that already exists and is not empty
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum ExistingDir {
+    /// Refuse to build into it
+    #[default]
+    Abort,
+    /// Build into it, overwriting any file the template provides
+    Force,
+    /// Build into it, only adding the template files that are missing
+    Merge,
+}
+
 impl ProjectBuilder {
-    pub fn build(config: &ScaffoldConfig) {
+    pub fn build(config: &ScaffoldConfig, options: &BuildOptions) -> Result<()> {
         println!("Building project...");
-        make_folders(&config.root_dir, &config.subfolders);
-        std::env::set_current_dir(&config.root_dir).expect("Failed to set current directory");
+        let kept = check_existing(config, options.existing)?;
+        let original_dir = env::current_dir().context("Failed to read current directory")?;
+        let rollback = Rollback::record(&config.root_dir)?;
 
-        pre_install_commands(config).expect("Failed to run pre-install commands");
-        install_commands(config).expect("Failed to run install commands");
-        post_install_commands(config).expect("Failed to run post-install commands");
+        let result = Self::run_steps(config, &kept);
+        if result.is_err() {
+            // step out of the half-built project before removing or moving it
+            env::set_current_dir(&original_dir).with_context(|| {
+                format!(
+                    "Failed to return to {} after the build failed",
+                    original_dir.display()
+                )
+            })?;
+            rollback.undo(options.keep_on_failure);
+        } else if !kept.is_empty() {
+            println!(
+                "Kept {} existing file(s) instead of the template's:",
+                kept.len()
+            );
+            for file in &kept {
+                println!("  {}", config.root_dir.join(file).display());
+            }
+        }
+        result
+    }
+
+    fn run_steps(config: &ScaffoldConfig, kept: &[PathBuf]) -> Result<()> {
+        step("creating folders", || {
+            make_folders(&config.root_dir, &config.subfolders)
+        })?;
+        step("entering the project folder", || {
+            env::set_current_dir(&config.root_dir).with_context(|| {
+                format!(
+                    "Failed to set current directory to {}",
+                    config.root_dir.display()
+                )
+            })
+        })?;
+
+        step("copying before_install files", || {
+            pre_install_commands(config, kept)
+        })?;
+        step("installing dependencies", || install_commands(config))?;
+        step("running post-install steps", || {
+            post_install_commands(config, kept)
+        })
     }
 }
 
-fn pre_install_commands(config: &ScaffoldConfig) -> Result<()> {
+/// Checks the project folder before anything is written. Returns the files (relative to the
+/// project root) the build must leave alone, which is only ever non-empty when merging.
+pub fn check_existing(config: &ScaffoldConfig, mode: ExistingDir) -> Result<Vec<PathBuf>> {
+    let root = &config.root_dir;
+    if !root.is_dir() || is_empty_dir(root)? {
+        return Ok(vec![]);
+    }
+    match mode {
+        ExistingDir::Abort => Err(Error::Config(format!(
+            "{} already exists and is not empty, pass --force to overwrite it or --merge to only add missing files",
+            root.display()
+        ))),
+        ExistingDir::Force => Ok(vec![]),
+        ExistingDir::Merge => Ok(get_written_files(config)
+            .into_iter()
+            .filter(|file| root.join(file).exists())
+            .collect()),
+    }
+}
+
+fn is_empty_dir(dir: &Path) -> Result<bool> {
+    let mut entries =
+        std::fs::read_dir(dir).with_context(|| format!("Failed to read {}", dir.display()))?;
+    Ok(entries.next().is_none())
+}
+
+/// Every file the templates write (or remove) in the project, relative to the project root
+fn get_written_files(config: &ScaffoldConfig) -> Vec<PathBuf> {
+    let mut files = vec![];
+    for dir in ["before_install", "after_install"] {
+        for (file, _) in file_system::list_layered_files(&config.template_dirs, dir) {
+            files.push(file);
+        }
+    }
+    for addon in &config.addons {
+        for (file, _) in file_system::list_layered_files(&config.template_dirs, &addon.files) {
+            files.push(file);
+        }
+    }
+    if config.containers {
+        files.append(&mut container::get_docker_files(&config.template_dirs));
+    }
+    files.append(&mut get_boilerplate_files(config));
+    files.push(PathBuf::from(ANSWERS_FILE));
+    files.sort();
+    files.dedup();
+    files
+}
+
+fn step(name: &'static str, action: impl FnOnce() -> Result<()>) -> Result<()> {
+    action().map_err(|err| Error::Step {
+        step: name,
+        source: Box::new(err),
+    })
+}
+
+/// What existed of the project folder before the build, so a failed build can undo what it
+/// created. Files the build overwrote (with `--force`) or changed (with `--merge`) stay as they
+/// were left.
+struct Rollback {
+    root: PathBuf,
+    /// Folders of the project path that did not exist yet, outermost first
+    created_dirs: Vec<PathBuf>,
+    /// Everything inside the project folder before the build
+    existing: HashSet<PathBuf>,
+}
+
+impl Rollback {
+    fn record(root: &Path) -> Result<Self> {
+        let mut created_dirs = vec![];
+        for ancestor in root.ancestors() {
+            if ancestor.as_os_str().is_empty() || ancestor.exists() {
+                break;
+            }
+            created_dirs.insert(0, ancestor.to_path_buf());
+        }
+        let existing = match root.is_dir() {
+            true => file_system::list_entries(root)
+                .with_context(|| format!("Failed to read {}", root.display()))?
+                .into_iter()
+                .collect(),
+            false => HashSet::new(),
+        };
+        Ok(Self {
+            root: root.to_path_buf(),
+            created_dirs,
+            existing,
+        })
+    }
+
+    /// Removes every file and folder the build created, or with `keep` moves a project folder
+    /// the build created aside
+    fn undo(&self, keep: bool) {
+        if keep {
+            match self.created_dirs.first() {
+                Some(dir) => move_aside(dir),
+                None => println!(
+                    "->> {} existed before the build, the partially built project was left in place",
+                    self.root.display()
+                ),
+            }
+            return;
+        }
+
+        // every folder is listed before its contents, so removing in reverse empties it first
+        let mut created = self.created_dirs.clone();
+        created.extend(
+            file_system::list_entries(&self.root)
+                .unwrap_or_default()
+                .into_iter()
+                .filter(|path| !self.existing.contains(path)),
+        );
+        let mut removed = 0;
+        for path in created.iter().rev() {
+            let is_dir = path
+                .symlink_metadata()
+                .is_ok_and(|metadata| metadata.is_dir());
+            let result = match is_dir {
+                true => std::fs::remove_dir(path),
+                false => std::fs::remove_file(path),
+            };
+            match result {
+                Ok(()) => removed += 1,
+                Err(err) => eprintln!("warning: could not remove {}: {}", path.display(), err),
+            }
+        }
+        println!(
+            "->> Removed {} file(s) and folder(s) the build created in {}",
+            removed,
+            self.root.display()
+        );
+    }
+}
+
+fn move_aside(dir: &Path) {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default();
+    let parent = dir.parent().unwrap_or(Path::new(""));
+    let failed_dir = parent.join(format!(".germinate-failed-{}", timestamp));
+    match std::fs::rename(dir, &failed_dir) {
+        Ok(()) => println!(
+            "->> Moved partially built project {} to {}",
+            dir.display(),
+            failed_dir.display()
+        ),
+        Err(err) => eprintln!(
+            "warning: could not move {} to {}: {}",
+            dir.display(),
+            failed_dir.display(),
+            err
+        ),
+    }
+}
+
+fn pre_install_commands(config: &ScaffoldConfig, kept: &[PathBuf]) -> Result<()> {
     println!("Running pre-install commands...");
-    let pre_install_path = config.template_dir.join("before_install");
-    file_system::copy_dir_all(pre_install_path, env::current_dir().unwrap())
+    copy_template_files(&config.template_dirs, "before_install", kept)
 }
 
 fn install_commands(config: &ScaffoldConfig) -> Result<()> {
     println!("Installing dependencies...");
-    for mut command in get_install_commands(config) {
+    for mut command in get_install_commands(config)? {
         println!("Running command: {:?}", command);
-        let output = command.output().expect("Failed to execute command");
+        let output = run_command(&mut command)?;
         println!("->> STDOUT: {}", String::from_utf8_lossy(&output.stdout));
         println!("->> STDERR: {}", String::from_utf8_lossy(&output.stderr));
     }
     Ok(())
 }
 
-fn post_install_commands(config: &ScaffoldConfig) -> Result<()> {
+fn post_install_commands(config: &ScaffoldConfig, kept: &[PathBuf]) -> Result<()> {
     println!("Running post-install commands...");
 
     // stack specific commands
-    let stack = &config.user_options.stack;
-    match stack {
-        StackTemplate::RSAPI | StackTemplate::TSAPI => {
-            if config.user_options.template_engine {
-                println!("->> installing template engine");
-                match stack {
-                    StackTemplate::TSAPI => {
-                        let mut command = Command::new("bun");
-                        command.args(&["add", "handlebars", "--features", "dir_source"]);
-                        command.output().expect("Failed to execute command");
-                    }
-                    StackTemplate::RSAPI => {
-                        let mut command = Command::new("cargo");
-                        command.args(&["add", "handlebars"]);
-                        command.output().expect("Failed to execute command");
-                    }
-                    _ => (),
-                }
-            }
-            if config.user_options.spa {
-                println!("->> Creating Vue/Vite SPA");
-                let mut command = Command::new("bun");
-                command.args(&["create", "vue@latest"]);
-                command
-                    .spawn()
-                    .unwrap()
-                    .wait()
-                    .expect("Failed to execute command");
-            }
+    if config.user_options.template_engine {
+        println!("->> installing template engine");
+        for mut command in get_template_engine_commands(config) {
+            run_command(&mut command)?;
+        }
+    }
+    if let Some(mut command) = get_spa_command(config) {
+        println!("->> Creating Vue/Vite SPA");
+        let status = command
+            .status()
+            .map_err(|err| command_error(&command, err))?;
+        if !status.success() {
+            return Err(Error::Command {
+                command: format_command(&command),
+                status: status.code(),
+                stderr: String::new(),
+            });
         }
-        _ => (),
     }
 
     // general commands
     println!("->> Removing boilerplate files...");
-    match stack {
-        StackTemplate::TSAPI | StackTemplate::TSCLI => {
-            std::fs::remove_file("index.ts").ok();
+    for file in get_boilerplate_files(config) {
+        if !kept.contains(&file) {
+            std::fs::remove_file(file).ok();
         }
-        _ => {}
     }
     println!("->> Copying Post-install templates...");
-    let post_install_path = config.template_dir.join("after_install");
-    file_system::copy_dir_all(post_install_path, env::current_dir().unwrap())
-        .expect("unable to copy dir");
+    copy_template_files(&config.template_dirs, "after_install", kept)?;
+
+    for addon in &config.addons {
+        println!("->> Adding addon {}...", addon.name);
+        copy_template_files(&config.template_dirs, &addon.files, kept)?;
+        for mut command in module::generate_then_cmds(&addon.then) {
+            run_command(&mut command)?;
+        }
+    }
 
     if config.containers {
-        ContainerBuilder::new(config).build();
+        ContainerBuilder::new(config).build(kept)?;
     }
 
     if let Some(npm_scripts) = &config.npm_scripts {
-        set_npm_scripts(npm_scripts);
+        set_npm_scripts(npm_scripts)?;
     }
 
-    create_repo();
+    if !kept.iter().any(|file| file == Path::new(ANSWERS_FILE)) {
+        println!("->> Recording answers...");
+        Answers::from(&config.user_options).save(Path::new(ANSWERS_FILE))?;
+    }
+
+    create_repo()
+}
 
+/// Copies `subfolder` of every template folder into the project, later folders overwriting the
+/// files of earlier ones
+fn copy_template_files(
+    template_dirs: &[PathBuf],
+    subfolder: impl AsRef<Path>,
+    kept: &[PathBuf],
+) -> Result<()> {
+    let project_dir = env::current_dir().context("Failed to read current directory")?;
+    let keep: Vec<PathBuf> = kept.iter().map(|file| project_dir.join(file)).collect();
+    for template_dir in template_dirs {
+        let src = template_dir.join(subfolder.as_ref());
+        if !src.is_dir() {
+            continue;
+        }
+        file_system::copy_dir_except(&src, &project_dir, &keep).with_context(|| {
+            format!(
+                "Failed to copy {} into {}",
+                src.display(),
+                project_dir.display()
+            )
+        })?;
+    }
     Ok(())
 }
 
-fn make_folders(root_dir: &PathBuf, subfolders: &Option<Vec<PathBuf>>) {
-    println!("Making folders...");
-    if let Some(folders) = subfolders {
-        for folder in folders {
-            let full_path = root_dir.join(folder);
-            println!("Creating folder: {:?}", full_path);
-            std::fs::create_dir_all(&full_path)
-                .expect(format!("Failed to create folder: {:?}", &full_path).as_str());
+/// Runs a command to completion, treating a non-zero exit status as an error
+pub fn run_command(command: &mut Command) -> Result<Output> {
+    let output = command
+        .output()
+        .map_err(|err| command_error(command, err))?;
+    if !output.status.success() {
+        return Err(Error::Command {
+            command: format_command(command),
+            status: output.status.code(),
+            stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+        });
+    }
+    Ok(output)
+}
+
+fn command_error(command: &Command, err: std::io::Error) -> Error {
+    Error::Command {
+        command: format_command(command),
+        status: None,
+        stderr: err.to_string(),
+    }
+}
+
+/// Formats a command the way it would be typed in a shell
+pub fn format_command(command: &Command) -> String {
+    let mut parts: Vec<String> = command
+        .get_envs()
+        .filter_map(|(key, value)| {
+            value.map(|value| format!("{}={}", key.to_string_lossy(), value.to_string_lossy()))
+        })
+        .collect();
+    parts.push(command.get_program().to_string_lossy().to_string());
+    parts.extend(command.get_args().map(|arg| {
+        let arg = arg.to_string_lossy();
+        if arg.is_empty() || arg.contains(char::is_whitespace) {
+            format!("'{}'", arg)
+        } else {
+            arg.to_string()
         }
+    }));
+    parts.join(" ")
+}
+
+pub fn get_template_engine_commands(config: &ScaffoldConfig) -> Vec<Command> {
+    if !config.user_options.template_engine {
+        return vec![];
+    }
+    if !config.user_options.stack.web {
+        return vec![];
+    }
+    if config.has_language(&Language::TypeScript) {
+        let mut command = Command::new("bun");
+        command.args(["add", "handlebars", "--features", "dir_source"]);
+        vec![command]
+    } else if config.has_language(&Language::Rust) {
+        let mut command = Command::new("cargo");
+        command.args(["add", "handlebars"]);
+        vec![command]
     } else {
-        println!("Creating root folder only: {:?}", &root_dir);
-        std::fs::create_dir_all(root_dir).unwrap();
+        vec![]
     }
 }
 
-fn get_install_commands(config: &ScaffoldConfig) -> Vec<Command> {
-    println!("Queueing install commands...");
+pub fn get_spa_command(config: &ScaffoldConfig) -> Option<Command> {
+    if config.user_options.stack.web && config.user_options.spa {
+        let mut command = Command::new("bun");
+        command.args(["create", "vue@latest"]);
+        Some(command)
+    } else {
+        None
+    }
+}
+
+/// Files generated by the init commands that the after_install templates replace
+pub fn get_boilerplate_files(config: &ScaffoldConfig) -> Vec<PathBuf> {
+    if config.has_language(&Language::TypeScript) {
+        vec![PathBuf::from("index.ts")]
+    } else {
+        vec![]
+    }
+}
+
+fn make_folders(root_dir: &Path, subfolders: &Option<Vec<PathBuf>>) -> Result<()> {
+    println!("Making folders...");
+    for full_path in get_folders(root_dir, subfolders) {
+        println!("Creating folder: {:?}", full_path);
+        std::fs::create_dir_all(&full_path)
+            .with_context(|| format!("Failed to create folder: {}", full_path.display()))?;
+    }
+    Ok(())
+}
+
+pub fn get_folders(root_dir: &Path, subfolders: &Option<Vec<PathBuf>>) -> Vec<PathBuf> {
+    match subfolders {
+        Some(folders) => folders.iter().map(|folder| root_dir.join(folder)).collect(),
+        None => vec![root_dir.to_path_buf()],
+    }
+}
+
+pub fn get_install_commands(config: &ScaffoldConfig) -> Result<Vec<Command>> {
     let mut commands = vec![];
 
     commands.append(&mut generate_init_cmds(config));
@@ -128,47 +459,59 @@ fn get_install_commands(config: &ScaffoldConfig) -> Vec<Command> {
     }
 
     if let Some(db_client) = &config.db_client {
-        commands.append(&mut db_client.get_install_commands(config));
+        commands.append(&mut db_client.get_install_commands(config)?);
     }
 
     commands.append(&mut generate_linter_cmds(&config.linters));
 
-    commands
+    Ok(commands)
 }
 
 fn generate_init_cmds(config: &ScaffoldConfig) -> Vec<Command> {
     let mut commands = vec![];
 
-    if config.cargo_deps.is_some() {
-        println!("Generating Cargo init...");
+    // an existing project being merged into or overwritten already has its manifest
+    if config.uses_package_manager(&PackageManager::Cargo)
+        && !config.root_dir.join("Cargo.toml").exists()
+    {
+        // name the package after the project rather than the output folder
         let mut cargo_init = Command::new("cargo");
-        cargo_init.arg("init");
+        cargo_init.args(["init", "--name", &slugify(&config.user_options.app_name)]);
         commands.push(cargo_init);
     }
-    if config.npm_deps.is_some() {
-        println!("Generating NPM init...");
-        let mut npm_init = Command::new("bun");
-        npm_init.args(&["init", "-y"]);
-        commands.push(npm_init);
+    if config.uses_package_manager(&PackageManager::Npm) {
+        if !config.root_dir.join("package.json").exists() {
+            let mut npm_init = Command::new("bun");
+            npm_init.args(["init", "-y"]);
+            commands.push(npm_init);
+        }
 
         let mut package_name = Command::new("npm");
-        package_name.args(&["pkg", "set", "name", &config.user_options.app_name]);
+        package_name.args(["pkg", "set", "name", &config.user_options.app_name]);
         commands.push(package_name);
     }
 
     commands
 }
 
-fn set_npm_scripts(scripts: &HashMap<String, String>) {
+fn set_npm_scripts(scripts: &HashMap<String, String>) -> Result<()> {
     println!("Setting NPM scripts...");
+    for mut command in get_npm_script_commands(scripts) {
+        run_command(&mut command)?;
+    }
+    Ok(())
+}
+
+pub fn get_npm_script_commands(scripts: &HashMap<String, String>) -> Vec<Command> {
+    let mut commands = vec![];
     for (name, script) in scripts {
         let mut command = Command::new("npm");
         command
-            .args(&["pkg", "set"])
-            .arg(format!("scripts.{}={}", name, script))
-            .output()
-            .expect("Failed to set npm scripts");
+            .args(["pkg", "set"])
+            .arg(format!("scripts.{}={}", name, script));
+        commands.push(command);
     }
+    commands
 }
 
 fn generate_linter_cmds(linters: &Vec<Linter>) -> Vec<Command> {
@@ -179,22 +522,122 @@ fn generate_linter_cmds(linters: &Vec<Linter>) -> Vec<Command> {
     commands
 }
 
-fn create_repo() {
+/// Creates the repo and initial commit. The project is complete at this point, so a git failure
+/// (e.g. no user.email configured) is reported without failing the build and rolling it back.
+fn create_repo() -> Result<()> {
     println!("Creating git repo...");
-    let mut command = Command::new("git");
-    command.args(&["init"]);
-    command.output().expect("Failed to create git repo");
-
-    let mut command = Command::new("git");
-    command.args(&["checkout", "-b", "main"]);
-    command.output().expect("Failed to create main branch");
-
-    println!("Creating initial commit...");
-    let mut command = Command::new("git");
-    command.args(&["add", "."]);
-    command.output().expect("Failed to add files to git repo");
-
-    let mut command = Command::new("git");
-    command.args(&["commit", "-m", "Initial commit"]);
-    command.output().expect("Failed to create initial commit");
+    for mut command in get_repo_commands() {
+        if let Err(err) = run_command(&mut command) {
+            eprintln!("warning: {}", err);
+            eprintln!(
+                "warning: skipping the remaining git setup, the project is otherwise complete"
+            );
+            break;
+        }
+    }
+    Ok(())
+}
+
+pub fn get_repo_commands() -> Vec<Command> {
+    [
+        vec!["init"],
+        vec!["checkout", "-b", "main"],
+        vec!["add", "."],
+        vec!["commit", "-m", "Initial commit"],
+    ]
+    .into_iter()
+    .map(|args| {
+        let mut command = Command::new("git");
+        command.args(args);
+        command
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    #[test]
+    fn check_existing_project_folder() {
+        let scratch = tempfile::tempdir().expect("Error creating scratch dir");
+        let config = ScaffoldConfig::for_stack("rscli", &scratch.path().join("billing"));
+        let check = |mode| check_existing(&config, mode);
+
+        // a missing or empty folder is built into whatever the mode
+        assert!(check(ExistingDir::Abort)
+            .expect("Missing folder refused")
+            .is_empty());
+        fs::create_dir(&config.root_dir).expect("Error creating folder");
+        assert!(check(ExistingDir::Abort)
+            .expect("Empty folder refused")
+            .is_empty());
+
+        fs::write(config.root_dir.join(".gitignore"), "target").expect("Error writing file");
+        fs::write(config.root_dir.join(ANSWERS_FILE), "").expect("Error writing file");
+        fs::write(config.root_dir.join("notes.txt"), "").expect("Error writing file");
+        match check(ExistingDir::Abort) {
+            Err(Error::Config(message)) => {
+                assert!(message.contains("--force") && message.contains("--merge"))
+            }
+            other => panic!("Non-empty folder not refused: {:?}", other),
+        }
+        assert!(check(ExistingDir::Force).expect("Force refused").is_empty());
+        // only the files the templates would write are kept, others aren't touched anyway
+        assert_eq!(
+            check(ExistingDir::Merge).expect("Merge refused"),
+            [PathBuf::from(ANSWERS_FILE), PathBuf::from(".gitignore")]
+        );
+    }
+
+    #[test]
+    fn rollback_removes_a_new_project_folder() {
+        let scratch = tempfile::tempdir().expect("Error creating scratch dir");
+        let root = scratch.path().join("apps").join("billing");
+        let rollback = Rollback::record(&root).expect("Error recording project folder");
+
+        fs::create_dir_all(root.join("src")).expect("Error creating folders");
+        fs::write(root.join("src/main.rs"), "fn main() {}").expect("Error writing file");
+        rollback.undo(false);
+
+        assert!(!scratch.path().join("apps").exists());
+        assert!(scratch.path().is_dir());
+    }
+
+    #[test]
+    fn rollback_only_removes_what_the_build_created() {
+        let scratch = tempfile::tempdir().expect("Error creating scratch dir");
+        let root = scratch.path();
+        fs::create_dir(root.join("src")).expect("Error creating folder");
+        fs::write(root.join("src/lib.rs"), "").expect("Error writing file");
+        fs::write(root.join("README.md"), "notes").expect("Error writing file");
+        let rollback = Rollback::record(root).expect("Error recording project folder");
+
+        fs::write(root.join("README.md"), "overwritten").expect("Error writing file");
+        fs::write(root.join("src/main.rs"), "").expect("Error writing file");
+        fs::create_dir_all(root.join("target/debug")).expect("Error creating folders");
+        fs::write(root.join("target/debug/billing"), "").expect("Error writing file");
+        fs::write(root.join("Cargo.toml"), "").expect("Error writing file");
+
+        rollback.undo(true);
+        assert!(root.join("target/debug/billing").exists());
+
+        rollback.undo(false);
+        let mut left = file_system::list_entries(root).expect("Error listing project");
+        left.sort();
+        assert_eq!(
+            left,
+            [
+                root.join("README.md"),
+                root.join("src"),
+                root.join("src/lib.rs")
+            ]
+        );
+        assert_eq!(
+            fs::read_to_string(root.join("README.md")).expect("Error reading file"),
+            "overwritten"
+        );
+    }
 }
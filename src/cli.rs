@@ -0,0 +1,194 @@
+use std::path::PathBuf;
+
+use clap::{Args, Parser, Subcommand};
+use clap_complete::{ArgValueCandidates, Shell};
+
+use crate::{completions, dialogue::Database, expression::Variables};
+
+#[derive(Debug, Parser)]
+#[command(
+    name = "germinate",
+    version,
+    about = "A launchpad for accelerating from idea ----->>> code"
+)]
+pub struct Cli {
+    /// Directory containing the stack templates [env: GERMINATE_TEMPLATES]
+    #[arg(long, global = true, value_name = "DIR")]
+    pub templates_dir: Option<PathBuf>,
+
+    #[command(subcommand)]
+    pub command: Option<Commands>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Commands {
+    /// Scaffold a new project, prompting only for options not supplied as flags
+    New(NewArgs),
+    /// List the available stacks
+    List,
+    /// Show what a stack would produce: dependencies, scripts, folders and files
+    Show {
+        /// Name of the stack's folder in the templates directory
+        #[arg(add = ArgValueCandidates::new(completions::stack_candidates))]
+        stack: String,
+    },
+    /// Tools for stack template authors
+    Template {
+        #[command(subcommand)]
+        command: TemplateCommands,
+    },
+    /// Print the script that registers shell completions, e.g. `source <(germinate completions bash)`
+    Completions {
+        #[arg(value_enum)]
+        shell: Shell,
+    },
+    /// Print the man page, or write one page per subcommand with --out-dir
+    Man {
+        /// Write germinate.1 and a page for every subcommand into this folder
+        #[arg(long, value_name = "DIR")]
+        out_dir: Option<PathBuf>,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum TemplateCommands {
+    /// Create a stack in the templates directory with a commented manifest, skeleton folders
+    /// and a sample docker template
+    New {
+        /// Name of the stack, used as its folder name
+        name: String,
+    },
+    /// Check a stack: its manifest, docker templates, template folders and `then` commands.
+    /// Docker templates are rendered for every database and every combination of prompt choices
+    /// (bools both ways, each select option, none, each and all multiselect options). Past 256
+    /// combinations each prompt's choices are tried with the other prompts at their defaults.
+    /// String and int prompts only take their default. Exits with a non-zero status when a
+    /// problem is found.
+    Validate {
+        /// Stack folder to check, or the name of a stack in the templates directory
+        #[arg(add = ArgValueCandidates::new(completions::stack_candidates))]
+        stack: String,
+    },
+}
+
+#[derive(Debug, Clone, Default, Args)]
+pub struct NewArgs {
+    /// Stack to scaffold from, the name of its folder in the templates directory
+    #[arg(long, add = ArgValueCandidates::new(completions::stack_candidates))]
+    pub stack: Option<String>,
+
+    /// Name of the project, used for package and container names
+    #[arg(long)]
+    pub name: Option<String>,
+
+    /// Folder to create the project in, absolute or relative to the current folder
+    /// [default: the slugified project name]
+    #[arg(long, value_name = "PATH")]
+    pub output: Option<PathBuf>,
+
+    /// Database client to install
+    #[arg(long, value_enum, conflicts_with = "no_db")]
+    pub db: Option<Database>,
+
+    /// Scaffold without a database
+    #[arg(long)]
+    pub no_db: bool,
+
+    /// Use an ORM for the selected database
+    #[arg(long, num_args = 0..=1, default_missing_value = "true", value_name = "BOOL")]
+    pub orm: Option<bool>,
+
+    /// Add a Vue/Vite SPA (web stacks only)
+    #[arg(long, num_args = 0..=1, default_missing_value = "true", value_name = "BOOL")]
+    pub spa: Option<bool>,
+
+    /// Add a Handlebars template engine (web stacks only)
+    #[arg(long, num_args = 0..=1, default_missing_value = "true", value_name = "BOOL")]
+    pub template_engine: Option<bool>,
+
+    /// Generate Docker container files (web stacks only)
+    #[arg(long, num_args = 0..=1, default_missing_value = "true", value_name = "BOOL")]
+    pub containers: Option<bool>,
+
+    /// Install an addon declared by the stack (repeatable or comma separated)
+    #[arg(long = "addon", value_name = "NAME", value_delimiter = ',')]
+    pub addons: Option<Vec<String>>,
+
+    /// Answer a prompt declared by the stack, e.g. `--set auth=true` (repeatable, separate
+    /// multiselect options with commas)
+    #[arg(long = "set", value_name = "NAME=VALUE")]
+    pub set: Vec<String>,
+
+    /// Answers to the stack's prompts read from an answers file
+    #[arg(skip)]
+    pub prompt_answers: Variables,
+
+    /// Never prompt; options not supplied fall back to their defaults
+    #[arg(long)]
+    pub no_input: bool,
+
+    /// Replay the answers recorded in a previous run instead of prompting
+    #[arg(long, value_name = "FILE")]
+    pub answers: Option<PathBuf>,
+
+    /// Move a partially built project to .germinate-failed-<timestamp> instead of removing it
+    #[arg(long)]
+    pub keep_on_failure: bool,
+
+    /// Write into an existing non-empty project folder, overwriting files the template provides
+    #[arg(long, conflicts_with = "merge")]
+    pub force: bool,
+
+    /// Write into an existing non-empty project folder, only adding files that are missing
+    #[arg(long)]
+    pub merge: bool,
+
+    /// Print the scaffold plan without creating files or running commands
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// Also write the collected answers to this file
+    #[arg(long, value_name = "FILE")]
+    pub save_answers: Option<PathBuf>,
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::CommandFactory;
+
+    use super::*;
+
+    #[test]
+    fn verify_cli() {
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn parse_new_flags() {
+        let cli = Cli::try_parse_from([
+            "germinate",
+            "new",
+            "--stack",
+            "rsapi",
+            "--name",
+            "my-app",
+            "--db",
+            "postgres",
+            "--orm",
+            "--spa=false",
+            "--containers",
+        ])
+        .expect("Error parsing flags");
+
+        let Some(Commands::New(args)) = cli.command else {
+            panic!("Expected new subcommand");
+        };
+        assert_eq!(args.stack.as_deref(), Some("rsapi"));
+        assert_eq!(args.name.as_deref(), Some("my-app"));
+        assert!(matches!(args.db, Some(Database::Postgres)));
+        assert_eq!(args.orm, Some(true));
+        assert_eq!(args.spa, Some(false));
+        assert_eq!(args.template_engine, None);
+        assert_eq!(args.containers, Some(true));
+    }
+}
@@ -0,0 +1,98 @@
+use std::{
+    io::{self, Write},
+    path::Path,
+};
+
+use clap::CommandFactory;
+use clap_complete::{
+    env::{EnvCompleter, Shells},
+    CompletionCandidate, Shell,
+};
+
+use crate::{
+    cli::Cli,
+    error::{Context, Error, Result},
+    stack, templates,
+};
+
+/// Environment variable the shell sets when it asks germinate for completions
+pub const COMPLETE_ENV: &str = "COMPLETE";
+
+/// Prints the script that registers germinate's completions with `shell`. The script asks
+/// germinate for completions as they are needed, so stack names always match the templates
+/// folder.
+pub fn print_completions(shell: Shell) -> Result<()> {
+    let shells = Shells::builtins();
+    let completer = shells
+        .completer(&shell.to_string())
+        .ok_or_else(|| Error::Config(format!("No completion support for {}", shell)))?;
+    let mut script = vec![];
+    write_registration(completer, &mut script).context("Failed to write completion script")?;
+    io::stdout()
+        .write_all(&script)
+        .context("Failed to write completion script")
+}
+
+fn write_registration(completer: &dyn EnvCompleter, script: &mut Vec<u8>) -> io::Result<()> {
+    let name = Cli::command().get_name().to_string();
+    completer.write_registration(COMPLETE_ENV, &name, &name, &name, script)
+}
+
+/// Stacks offered for `--stack`, `show` and `template validate`, with their labels as help.
+/// The templates folder is found through `GERMINATE_TEMPLATES` and the default locations, a
+/// `--templates-dir` on the line being completed isn't seen.
+pub fn stack_candidates() -> Vec<CompletionCandidate> {
+    match templates::resolve_templates_dir(None) {
+        Ok(templates_dir) => get_stack_candidates(&templates_dir),
+        Err(_) => vec![],
+    }
+}
+
+fn get_stack_candidates(templates_dir: &Path) -> Vec<CompletionCandidate> {
+    stack::discover(templates_dir)
+        .map(|discovered| discovered.stacks)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|stack| CompletionCandidate::new(stack.name).help(Some(stack.label.into())))
+        .collect()
+}
+
+/// Prints the germinate man page, or writes a page for germinate and every subcommand to `out_dir`
+pub fn print_man(out_dir: Option<&Path>) -> Result<()> {
+    let command = Cli::command();
+    match out_dir {
+        Some(dir) => {
+            std::fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create {}", dir.display()))?;
+            clap_mangen::generate_to(command, dir)
+                .with_context(|| format!("Failed to write man pages to {}", dir.display()))?;
+            println!("->> Man pages written to {}", dir.display());
+            Ok(())
+        }
+        None => clap_mangen::Man::new(command)
+            .render(&mut io::stdout())
+            .context("Failed to write man page"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn completes_stacks_of_the_templates_folder() {
+        let templates_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("templates");
+        let stacks = get_stack_candidates(&templates_dir)
+            .iter()
+            .map(|candidate| candidate.get_value().to_string_lossy().to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(stacks, ["tsapi", "rsapi", "rscli", "tscli"]);
+
+        let shells = Shells::builtins();
+        let mut script = vec![];
+        write_registration(shells.completer("bash").unwrap(), &mut script)
+            .expect("Error writing registration");
+        let script = String::from_utf8(script).unwrap();
+        assert!(script.contains("COMPLETE=\"bash\""), "{}", script);
+    }
+}
@@ -1,25 +1,67 @@
-use std::{collections::HashMap, env, path::PathBuf, vec};
+use std::{collections::HashMap, path::PathBuf};
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
 
 use crate::{
+    addons::{self, Addon},
     db_client::DbClient,
-    dialogue::StackTemplate,
     dialogue::{Database, UserOptions},
+    error::{Context, Error, Result},
     linters::Linter,
     module::Module,
-    toml_parser::TomlTemplate,
 };
 
 type NpmDeps = Vec<Module>;
 type CargoDeps = Vec<Module>;
 pub type PackageScripts = HashMap<String, String>;
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
 pub enum Language {
     Rust,
     JavaScript,
     TypeScript,
 }
 
+impl Language {
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            Language::Rust => "rust",
+            Language::JavaScript => "javascript",
+            Language::TypeScript => "typescript",
+        }
+    }
+}
+
+/// Package managers a stack installs its dependencies with, named after their `deps` key
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum PackageManager {
+    Cargo,
+    /// npm packages, installed with bun
+    Npm,
+}
+
+impl PackageManager {
+    pub const NAMES: [&'static str; 2] = ["cargo", "npm"];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "cargo" => Some(PackageManager::Cargo),
+            "npm" => Some(PackageManager::Npm),
+            _ => None,
+        }
+    }
+
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            PackageManager::Cargo => "cargo",
+            PackageManager::Npm => "npm",
+        }
+    }
+}
+
 #[allow(unused)]
 #[derive(Debug, Clone)]
 pub struct ScaffoldConfig {
@@ -27,7 +69,10 @@ pub struct ScaffoldConfig {
     pub title: String,
     pub root_dir: PathBuf,
     pub template_dir: PathBuf,
+    /// Template folders files are copied and rendered from, in order
+    pub template_dirs: Vec<PathBuf>,
     pub languages: Vec<Language>,
+    pub package_managers: Vec<PackageManager>,
     pub db: Option<Database>,
     pub db_client: Option<DbClient>,
     pub linters: Vec<Linter>,
@@ -37,19 +82,20 @@ pub struct ScaffoldConfig {
     pub cargo_deps: Option<CargoDeps>,
     pub subfolders: Option<Vec<PathBuf>>,
     pub containers: bool,
+    /// Addons picked by the user, their deps and scripts are already merged into the stack's
+    pub addons: Vec<Addon>,
 }
 
 impl ScaffoldConfig {
-    pub fn new(options: UserOptions) -> Self {
+    pub fn new(options: UserOptions) -> Result<Self> {
         let title = options.app_name.clone();
-        let root_dir = PathBuf::from(&options.output_dir);
-        let template_dir = env::current_exe()
-            .unwrap()
-            .parent()
-            .unwrap()
-            .join(options.stack.get_path().parent().unwrap());
-
-        let toml = TomlTemplate::new(&template_dir.join("stack_template.toml"));
+        // the builder changes into the project folder, so resolve it against the current one now
+        let root_dir = std::path::absolute(&options.output_dir)
+            .with_context(|| format!("Failed to resolve {}", options.output_dir.display()))?;
+        let template_dir = options.stack.template_dir.clone();
+        let template_dirs = options.stack.template_dirs.clone();
+
+        let toml = &options.stack.template;
         let subfolders = toml.get_subfolders().cloned();
         let dependencies = toml.get_dependencies();
         let scripts = match toml.get_scripts() {
@@ -57,42 +103,87 @@ impl ScaffoldConfig {
             None => HashMap::new(),
         };
 
-        let npm_scripts = scripts.get("npm").cloned();
-        let cargo_scripts = scripts.get("cargo").cloned();
+        let mut npm_scripts = scripts.get("npm").cloned();
+        let mut cargo_scripts = scripts.get("cargo").cloned();
 
-        let npm_deps = dependencies.get("npm").unwrap().clone();
-        let cargo_deps = dependencies.get("cargo").unwrap().clone();
+        let missing = |key| {
+            Error::Config(format!(
+                "No {} dependencies table read from {}",
+                key,
+                template_dir.display()
+            ))
+        };
+        let mut npm_deps = dependencies
+            .get("npm")
+            .ok_or_else(|| missing("npm"))?
+            .clone();
+        let mut cargo_deps = dependencies
+            .get("cargo")
+            .ok_or_else(|| missing("cargo"))?
+            .clone();
+
+        let addons: Vec<Addon> = options
+            .stack
+            .addons
+            .iter()
+            .filter(|addon| options.addons.contains(&addon.name))
+            .cloned()
+            .collect();
+        for addon in &addons {
+            addons::merge_deps(&mut npm_deps, &addon.npm_deps);
+            addons::merge_deps(&mut cargo_deps, &addon.cargo_deps);
+            if let Some(scripts) = addon.scripts.get("npm") {
+                npm_scripts
+                    .get_or_insert_with(HashMap::new)
+                    .extend(scripts.clone());
+            }
+            if let Some(scripts) = addon.scripts.get("cargo") {
+                cargo_scripts
+                    .get_or_insert_with(HashMap::new)
+                    .extend(scripts.clone());
+            }
+        }
+
+        let variables = options.variables();
+        for deps in [&mut npm_deps, &mut cargo_deps].into_iter().flatten() {
+            deps.retain(|dep| {
+                dep.when
+                    .as_ref()
+                    .is_none_or(|when| when.evaluate(&variables))
+            });
+        }
 
         let db = options.db.clone();
+        let rust = options.stack.has_language(&Language::Rust);
 
         let db_client = match &db {
             Some(db_platform) => match db_platform {
-                Database::Postgres => match options.stack {
-                    StackTemplate::RSAPI | StackTemplate::RSCLI => match options.orm {
+                Database::Postgres => match rust {
+                    true => match options.orm {
                         true => Some(DbClient::Diesel),
                         false => Some(DbClient::Sqlx),
                     },
-                    _ => match options.orm {
+                    false => match options.orm {
                         true => Some(DbClient::Prisma),
                         false => Some(DbClient::Slonik),
                     },
                 },
-                Database::Sqlite => match options.stack {
-                    StackTemplate::RSAPI | StackTemplate::RSCLI => match options.orm {
+                Database::Sqlite => match rust {
+                    true => match options.orm {
                         true => Some(DbClient::Diesel),
                         false => Some(DbClient::Sqlx),
                     },
-                    _ => match options.orm {
+                    false => match options.orm {
                         true => Some(DbClient::Prisma),
                         false => Some(DbClient::BetterSqlite),
                     },
                 },
-                Database::Mongo => match options.stack {
-                    StackTemplate::RSAPI | StackTemplate::RSCLI => match options.orm {
-                        true => panic!("No Rust ORM for MongoDB"),
+                Database::Mongo => match rust {
+                    true => match options.orm {
+                        true => return Err(Error::Config("No Rust ORM for MongoDB".to_string())),
                         false => Some(DbClient::MongoDb),
                     },
-                    _ => match options.orm {
+                    false => match options.orm {
                         true => Some(DbClient::Mongoose),
                         false => Some(DbClient::MongoDb),
                     },
@@ -101,31 +192,26 @@ impl ScaffoldConfig {
             None => None,
         };
 
-        let languages = match options.stack {
-            StackTemplate::RSAPI => vec![Language::Rust],
-            StackTemplate::RSCLI => vec![Language::Rust],
-            _ => vec![Language::TypeScript, Language::JavaScript],
-        };
+        let languages = options.stack.languages.clone();
+        let package_managers = options.stack.package_managers.clone();
 
-        let linters = match options.stack {
-            StackTemplate::TSCLI => vec![Linter::ESLint],
-            StackTemplate::TSAPI => match options.spa || options.template_engine {
-                true => {
-                    vec![Linter::ESLint, Linter::Stylelint]
-                }
-                false => {
-                    vec![Linter::ESLint]
-                }
-            },
-            StackTemplate::RSCLI | StackTemplate::RSAPI => {
-                vec![Linter::Clippy]
-            }
-        };
+        let linters = options
+            .stack
+            .linters
+            .iter()
+            .filter(|spec| {
+                spec.when
+                    .as_ref()
+                    .is_none_or(|when| when.evaluate(&variables))
+            })
+            .map(|spec| spec.linter.clone())
+            .collect();
 
-        Self {
+        Ok(Self {
             title,
             root_dir,
             languages,
+            package_managers,
             db,
             db_client,
             linters,
@@ -135,12 +221,40 @@ impl ScaffoldConfig {
             cargo_deps,
             subfolders,
             containers: options.containers,
+            addons,
             user_options: options.clone(),
             template_dir,
-        }
+            template_dirs,
+        })
     }
 
     pub fn has_language(&self, language: &Language) -> bool {
         self.languages.contains(language)
     }
+
+    pub fn uses_package_manager(&self, package_manager: &PackageManager) -> bool {
+        self.package_managers.contains(package_manager)
+    }
+}
+
+#[cfg(test)]
+impl ScaffoldConfig {
+    /// Config of a bundled stack with every optional feature left out
+    pub fn for_stack(stack: &str, output_dir: &std::path::Path) -> Self {
+        let stack = crate::stack::Stack::load(&std::path::Path::new("templates").join(stack))
+            .expect("Error loading stack");
+        let options = UserOptions {
+            stack,
+            output_dir: output_dir.to_path_buf(),
+            app_name: "billing".to_string(),
+            db: None,
+            orm: false,
+            spa: false,
+            template_engine: false,
+            containers: false,
+            addons: vec![],
+            prompt_answers: crate::expression::Variables::new(),
+        };
+        Self::new(options).expect("Error building config")
+    }
 }
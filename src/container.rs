@@ -1,7 +1,17 @@
 use serde::{Deserialize, Serialize};
-use std::{env, fs};
+use slug::slugify;
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
 
-use crate::{config::ScaffoldConfig, dialogue::Database, template_generator};
+use crate::{
+    config::ScaffoldConfig,
+    dialogue::Database,
+    error::{Context, Result},
+    expression::Variables,
+    file_system, template_generator,
+};
 
 pub struct ContainerBuilder {
     config: ScaffoldConfig,
@@ -14,8 +24,10 @@ impl ContainerBuilder {
         }
     }
 
-    pub fn build(&self) {
-        generate_dockerfiles(&self.config);
+    /// Renders the docker files, leaving the project files listed in `keep` (relative to the
+    /// project root) untouched
+    pub fn build(&self, keep: &[PathBuf]) -> Result<()> {
+        generate_dockerfiles(&self.config, keep)
     }
 }
 
@@ -27,20 +39,16 @@ pub struct DockerVariables {
     postgres: bool,
     mongo: bool,
     sqlite: bool,
+    /// Answers to the stack's own prompts
+    #[serde(flatten)]
+    answers: Variables,
 }
 
 impl crate::template_generator::TemplateData for DockerVariables {}
 
 impl DockerVariables {
-    pub fn new(db: &Option<Database>) -> Self {
-        let kebab_name = env::current_dir()
-            .unwrap()
-            .file_name()
-            .unwrap()
-            .to_str()
-            .unwrap()
-            .to_string();
-
+    pub fn new(app_name: &str, db: &Option<Database>, answers: &Variables) -> Self {
+        let kebab_name = slugify(app_name);
         let snake_name = kebab_name.replace("-", "_");
         Self {
             app_name: kebab_name,
@@ -51,32 +59,80 @@ impl DockerVariables {
                 .is_some_and(|db| matches!(db, Database::Postgres)),
             mongo: db.as_ref().is_some_and(|db| matches!(db, Database::Mongo)),
             sqlite: db.as_ref().is_some_and(|db| matches!(db, Database::Sqlite)),
+            answers: answers.clone(),
         }
     }
 }
 
-fn generate_dockerfiles(config: &ScaffoldConfig) {
+/// Lists the files `generate_dockerfiles` renders from the docker directories of a stack's template
+/// folders, relative to the project root
+pub fn get_docker_files(template_dirs: &[PathBuf]) -> Vec<PathBuf> {
+    file_system::list_layered_files(template_dirs, "docker")
+        .into_iter()
+        .map(|(file, _)| {
+            // docker-compose.yml is moved to the project root after rendering
+            if file == Path::new("docker-compose.yml") {
+                file
+            } else {
+                Path::new("docker").join(file)
+            }
+        })
+        .collect()
+}
+
+fn generate_dockerfiles(config: &ScaffoldConfig, keep: &[PathBuf]) -> Result<()> {
     println!("Generating Docker files...");
 
-    template_generator::generate_dir(
-        config.template_dir.join("docker"),
-        env::current_dir().unwrap().join("docker"),
-        &DockerVariables::new(&config.db),
-        true,
+    let project_dir = env::current_dir().context("Failed to read current directory")?;
+    let mut keep: Vec<PathBuf> = keep.iter().map(|file| project_dir.join(file)).collect();
+    let keep_compose = keep.contains(&project_dir.join("docker-compose.yml"));
+    if keep_compose {
+        // rendered into docker/ first, so an existing root compose file means skipping that too
+        keep.push(project_dir.join("docker/docker-compose.yml"));
+    }
+    let variables = DockerVariables::new(
+        &config.user_options.app_name,
+        &config.db,
+        &config.user_options.prompt_answers,
     );
+    // an extending stack's files are rendered over the ones of the stack it extends
+    for template_dir in &config.template_dirs {
+        let docker_dir = template_dir.join("docker");
+        if docker_dir.is_dir() {
+            template_generator::generate_dir(
+                docker_dir,
+                project_dir.join("docker"),
+                &variables,
+                true,
+                &keep,
+            )?;
+        }
+    }
 
+    if keep_compose {
+        return Ok(());
+    }
     println!("Moving docker-compose.yml to project root...");
-    fs::copy(
-        env::current_dir()
-            .unwrap()
-            .join("docker/docker-compose.yml"),
-        env::current_dir().unwrap().join("docker-compose.yml"),
-    )
-    .expect("Failed to copy docker-compose.yml to project root");
-    fs::remove_file(
-        env::current_dir()
-            .unwrap()
-            .join("docker/docker-compose.yml"),
+    fs::rename(
+        project_dir.join("docker/docker-compose.yml"),
+        project_dir.join("docker-compose.yml"),
     )
-    .expect("Failed to remove docker-compose.yml from docker directory");
+    .context("Failed to move docker-compose.yml to project root")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn docker_names_come_from_app_name() {
+        let variables = DockerVariables::new(
+            "Billing Service",
+            &Some(Database::Postgres),
+            &Variables::new(),
+        );
+        assert_eq!(variables.app_name, "billing-service");
+        assert_eq!(variables.deps_name, "billing_service");
+        assert!(variables.database && variables.postgres);
+    }
 }
@@ -3,6 +3,7 @@ use std::process::Command;
 use crate::{
     config::{Language, ScaffoldConfig},
     dialogue::Database,
+    error::{Error, Result},
 };
 
 #[derive(Debug, Clone)]
@@ -17,26 +18,26 @@ pub enum DbClient {
 }
 
 impl DbClient {
-    pub fn get_install_commands(&self, config: &ScaffoldConfig) -> Vec<Command> {
+    pub fn get_install_commands(&self, config: &ScaffoldConfig) -> Result<Vec<Command>> {
         let db = config
             .db
             .as_ref()
-            .expect("No database specified for client");
-        match self {
+            .ok_or_else(|| Error::Config("No database specified for client".to_string()))?;
+        let commands = match self {
             DbClient::Diesel => {
                 let mut command = Command::new("cargo");
-                command.args(&["add", "diesel"]);
+                command.args(["add", "diesel"]);
 
                 match db {
-                    Database::Postgres => command.args(&["--features", "postgres"]),
-                    Database::Sqlite => command.args(&["--features", "sqlite"]),
+                    Database::Postgres => command.args(["--features", "postgres"]),
+                    Database::Sqlite => command.args(["--features", "sqlite"]),
                     Database::Mongo => &mut command,
                 };
                 vec![command]
             }
             DbClient::Sqlx => {
                 let mut command = Command::new("cargo");
-                command.args(&["add", "sqlx"]);
+                command.args(["add", "sqlx"]);
 
                 let mut features = String::from("runtime-tokio,tls-native-tls,time,migrate");
 
@@ -56,43 +57,46 @@ impl DbClient {
             }
             DbClient::Prisma => {
                 let mut command = Command::new("bun");
-                command.args(&["add", "prisma", "--dev"]);
+                command.args(["add", "prisma", "--dev"]);
 
                 let init_command = Command::new("bunx");
-                command.args(&["prisma", "init"]);
+                command.args(["prisma", "init"]);
 
                 vec![command, init_command]
             }
             DbClient::Slonik => {
                 if let Database::Postgres = db {
                     let mut command = Command::new("bun");
-                    command.args(&["add", "slonik", "--dev"]);
+                    command.args(["add", "slonik", "--dev"]);
                     vec![command]
                 } else {
-                    panic!("No Slonik support for non-Postgres databases")
+                    return Err(Error::Config(
+                        "No Slonik support for non-Postgres databases".to_string(),
+                    ));
                 }
             }
             DbClient::BetterSqlite => {
                 let mut command = Command::new("bun");
-                command.args(&["add", "better-sqlite3"]);
+                command.args(["add", "better-sqlite3"]);
                 vec![command]
             }
             DbClient::MongoDb => {
                 if config.has_language(&Language::Rust) {
                     let mut command = Command::new("cargo");
-                    command.args(&["add", "mongodb"]);
+                    command.args(["add", "mongodb"]);
                     vec![command]
                 } else {
                     let mut command = Command::new("bun");
-                    command.args(&["add", "mongodb"]);
+                    command.args(["add", "mongodb"]);
                     vec![command]
                 }
             }
             DbClient::Mongoose => {
                 let mut command = Command::new("bun");
-                command.args(&["add", "mongoose"]);
+                command.args(["add", "mongoose"]);
                 vec![command]
             }
-        }
+        };
+        Ok(commands)
     }
 }
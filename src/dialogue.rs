@@ -3,35 +3,26 @@ use std::{
     str::FromStr,
 };
 
+use clap::ValueEnum;
 use dialoguer::{theme::ColorfulTheme, Confirm, Input, Select};
+use serde::{Deserialize, Serialize};
 use slug::slugify;
-use strum::{EnumIter, EnumProperty, EnumString, EnumVariantNames, IntoEnumIterator, VariantNames};
-
-#[derive(Debug, Clone, EnumVariantNames, EnumString, EnumIter, EnumProperty)]
-pub enum StackTemplate {
-    #[strum(props(Label = "TypeScript Web App"))]
-    TSAPI,
-    #[strum(props(Label = "Rust Web App"))]
-    RSAPI,
-    #[strum(props(Label = "Rust CLI Tool"))]
-    RSCLI,
-    #[strum(props(Label = "TypeScript CLI Tool"))]
-    TSCLI,
-}
+use strum::{EnumString, EnumVariantNames, VariantNames};
+use toml::Value;
+
+use crate::{
+    addons,
+    cli::NewArgs,
+    config::Language,
+    error::{Context, Error, Result},
+    expression::Variables,
+    prompts,
+    stack::{self, Stack},
+};
 
-impl StackTemplate {
-    pub fn get_path(&self) -> PathBuf {
-        match self {
-            Self::RSAPI => PathBuf::from("templates/rsapi/stack_template.toml"),
-            Self::TSCLI => PathBuf::from("templates/tscli/stack_template.toml"),
-            Self::RSCLI => PathBuf::from("templates/rscli/stack_template.toml"),
-            Self::TSAPI => PathBuf::from("templates/tsapi/stack_template.toml"),
-        }
-    }
-}
 #[derive(Debug, Clone)]
 pub struct UserOptions {
-    pub stack: StackTemplate,
+    pub stack: Stack,
     pub output_dir: PathBuf,
     pub app_name: String,
     pub db: Option<Database>,
@@ -39,127 +30,221 @@ pub struct UserOptions {
     pub spa: bool,
     pub template_engine: bool,
     pub containers: bool,
+    /// Names of the addons picked by the user
+    pub addons: Vec<String>,
+    /// Answers to the prompts declared by the stack, passed to the templates as variables
+    pub prompt_answers: Variables,
 }
 
-pub fn get_user_config() -> Result<UserOptions, std::io::Error> {
-    let stack = get_stack();
-    let (spa, template_engine) = get_frontend(&stack);
-    let app_name = get_app_name();
-    let output_dir = slugify(&app_name);
-    let db = get_db();
+impl UserOptions {
+    /// The built-in options and prompt answers `when` conditions are evaluated against
+    pub fn variables(&self) -> Variables {
+        let mut variables = Variables::from([
+            ("stack".to_string(), Value::String(self.stack.name.clone())),
+            ("name".to_string(), Value::String(self.app_name.clone())),
+            ("orm".to_string(), Value::Boolean(self.orm)),
+            ("spa".to_string(), Value::Boolean(self.spa)),
+            (
+                "template_engine".to_string(),
+                Value::Boolean(self.template_engine),
+            ),
+            ("containers".to_string(), Value::Boolean(self.containers)),
+            (
+                "addons".to_string(),
+                Value::Array(self.addons.iter().cloned().map(Value::String).collect()),
+            ),
+        ]);
+        if let Some(db) = &self.db {
+            variables.insert("db".to_string(), Value::String(db.get_name()));
+        }
+        variables.extend(self.prompt_answers.clone());
+        variables
+    }
+}
+
+pub fn get_user_config(args: &NewArgs, templates_dir: &Path) -> Result<UserOptions> {
+    let interactive = !args.no_input && console::user_attended();
+
+    let stack = match &args.stack {
+        Some(name) => stack::find(templates_dir, name)?,
+        None if interactive => get_stack(templates_dir)?,
+        None => return Err(missing_option("--stack")),
+    };
+    let (spa, template_engine) = match stack.web {
+        true => (
+            confirm_unless_supplied(args.spa, interactive, "Would you like to use a SPA?")?,
+            confirm_unless_supplied(
+                args.template_engine,
+                interactive,
+                "Would you like to use a frontend template engine?",
+            )?,
+        ),
+        false => (false, false),
+    };
+    let app_name = match &args.name {
+        Some(name) => name.clone(),
+        None if interactive => get_app_name()?,
+        None => return Err(missing_option("--name")),
+    };
+    let output_dir = match &args.output {
+        Some(path) => path.clone(),
+        None => PathBuf::from(slugify(&app_name)),
+    };
+    let db = match &args.db {
+        Some(db) => Some(db.clone()),
+        None if args.no_db || !interactive => None,
+        None => get_db()?,
+    };
     let orm = match &db {
         Some(db) => match db {
-            Database::Mongo => match stack {
-                StackTemplate::RSCLI | StackTemplate::RSAPI => false,
-                _ => get_orm(),
-            },
-            _ => get_orm(),
+            Database::Mongo if stack.has_language(&Language::Rust) => {
+                if args.orm == Some(true) {
+                    return Err(Error::Config(
+                        "There is no Rust ORM for MongoDB, but an ORM was requested (via --orm or the answers file)".to_string(),
+                    ));
+                }
+                false
+            }
+            _ => confirm_unless_supplied(args.orm, interactive, ORM_PROMPT)?,
         },
         None => false,
     };
 
-    let containers = match stack {
-        StackTemplate::RSAPI | StackTemplate::TSAPI => containers_prompt(),
-        _ => false,
+    let containers = match stack.web {
+        true => confirm_unless_supplied(
+            args.containers,
+            interactive,
+            "Would you like to use Docker containers?",
+        )?,
+        false => false,
     };
+    let addons = addons::select(&stack.addons, args.addons.as_deref(), interactive)?;
 
-    let user_config = UserOptions {
+    let mut user_config = UserOptions {
         app_name,
         stack,
-        output_dir: Path::new(&output_dir).to_path_buf(),
+        output_dir,
         db,
         orm,
         spa,
         template_engine,
         containers,
+        addons,
+        prompt_answers: Variables::new(),
     };
 
-    println!("->> User Config generated: {:?}", user_config);
+    // flags win over answers files, like they do for the built-in options
+    let mut supplied = args.prompt_answers.clone();
+    supplied.append(&mut prompts::parse_set_flags(
+        &user_config.stack.prompts,
+        &args.set,
+    )?);
+    let mut variables = user_config.variables();
+    user_config.prompt_answers = prompts::answer(
+        &user_config.stack.prompts,
+        &supplied,
+        &mut variables,
+        interactive,
+    )?;
 
     Ok(user_config)
 }
 
-#[derive(Debug, Clone, EnumVariantNames, EnumString)]
+fn missing_option(flag: &str) -> Error {
+    Error::Config(format!("{} is required when prompts are disabled", flag))
+}
+
+#[derive(
+    Debug, Clone, PartialEq, EnumVariantNames, EnumString, ValueEnum, Serialize, Deserialize,
+)]
+#[serde(rename_all = "lowercase")]
 pub enum Database {
     Postgres,
     Mongo,
     Sqlite,
 }
 
-fn get_app_name() -> String {
+impl Database {
+    /// Name used for the database on the command line, in answers files and `when` conditions
+    pub fn get_name(&self) -> String {
+        match self {
+            Database::Postgres => "postgres",
+            Database::Mongo => "mongo",
+            Database::Sqlite => "sqlite",
+        }
+        .to_string()
+    }
+}
+
+const ORM_PROMPT: &str = "Would you like to use an ORM?";
+
+fn get_app_name() -> Result<String> {
     Input::<String>::new()
         .with_prompt("What is the name of your project?")
         .interact_text()
-        .unwrap()
+        .context("Failed to get project name from user")
 }
 
-fn get_stack() -> StackTemplate {
-    let mut stacks = StackTemplate::iter();
+fn get_stack(templates_dir: &Path) -> Result<Stack> {
+    let discovered = stack::discover(templates_dir)?;
+    discovered.warn_invalid();
+    let mut stacks = discovered.stacks;
+    if stacks.is_empty() {
+        return Err(Error::Config(format!(
+            "No stacks found in {}",
+            templates_dir.display()
+        )));
+    }
     let prompt_labels = stacks
-        .clone()
-        .map(|s| s.get_str("Label").unwrap())
+        .iter()
+        .map(|stack| stack.label.as_str())
         .collect::<Vec<_>>();
-    let stack_template_index = Select::with_theme(&ColorfulTheme::default())
+    let stack_index = Select::with_theme(&ColorfulTheme::default())
         .with_prompt("What stack would you like to use?")
         .items(&prompt_labels)
         .interact()
-        .expect("Failed to get stack selection from user");
-    stacks.nth(stack_template_index).unwrap()
+        .context("Failed to get stack selection from user")?;
+    Ok(stacks.swap_remove(stack_index))
 }
 
-fn get_db() -> Option<Database> {
-    let use_db = Confirm::with_theme(&ColorfulTheme::default())
-        .with_prompt("Would you like to use a database?")
-        .interact()
-        .ok()?;
+fn get_db() -> Result<Option<Database>> {
+    let use_db = confirm("Would you like to use a database?")?;
 
-    let db = if use_db {
-        Some(get_db_platform())
+    if use_db {
+        Ok(Some(get_db_platform()?))
     } else {
-        None
-    };
-
-    db
+        Ok(None)
+    }
 }
 
-fn get_db_platform() -> Database {
+fn get_db_platform() -> Result<Database> {
     let db_options = Database::VARIANTS;
     let db_index = Select::with_theme(&ColorfulTheme::default())
         .with_prompt("What database would you like to use?")
-        .items(&db_options)
-        .interact()
-        .expect("Failed to get db selection from user");
-    <Database as FromStr>::from_str(db_options[db_index]).expect("Invalid db name")
-}
-
-fn get_orm() -> bool {
-    Confirm::with_theme(&ColorfulTheme::default())
-        .with_prompt("Would you like to use an ORM?")
+        .items(db_options)
         .interact()
-        .expect("Failed to get ORM selection from user")
+        .context("Failed to get db selection from user")?;
+    <Database as FromStr>::from_str(db_options[db_index])
+        .map_err(|_| Error::Config(format!("Unknown database `{}`", db_options[db_index])))
 }
 
-fn get_frontend(stack: &StackTemplate) -> (bool, bool) {
-    match stack {
-        StackTemplate::RSAPI | StackTemplate::TSAPI => {
-            let spa = Confirm::with_theme(&ColorfulTheme::default())
-                .with_prompt("Would you like to use a SPA?")
-                .interact()
-                .expect("Failed to get SPA selection from user");
-
-            let template = Confirm::with_theme(&ColorfulTheme::default())
-                .with_prompt("Would you like to use a frontend template engine?")
-                .interact()
-                .expect("Failed to get template engine selection from user");
-            return (spa, template);
-        }
-        _ => return (false, false),
+/// Uses the value supplied by flag or answers file, otherwise asks the user. Without prompts an
+/// unanswered question defaults to no.
+fn confirm_unless_supplied(
+    supplied: Option<bool>,
+    interactive: bool,
+    prompt: &str,
+) -> Result<bool> {
+    match supplied {
+        Some(value) => Ok(value),
+        None if interactive => confirm(prompt),
+        None => Ok(false),
     }
 }
 
-fn containers_prompt() -> bool {
+fn confirm(prompt: &str) -> Result<bool> {
     Confirm::with_theme(&ColorfulTheme::default())
-        .with_prompt("Would you like to use Docker containers?")
+        .with_prompt(prompt)
         .interact()
-        .expect("Failed to get containers selection from user")
+        .with_context(|| format!("Failed to get answer to \"{}\" from user", prompt))
 }
@@ -0,0 +1,185 @@
+use std::{fmt, io, path::PathBuf};
+
+use crate::schema::SchemaError;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Every failure germinate reports. Each variant maps to its own exit code so wrapper scripts
+/// can tell a bad template apart from a failed install command. Code 2 is left to clap, which
+/// exits with it on invalid command line usage.
+#[derive(Debug)]
+pub enum Error {
+    /// Invalid or conflicting options, answers files or templates directory
+    Config(String),
+    /// A stack_template.toml that can't be read into a stack
+    TemplateParse { path: PathBuf, message: String },
+    /// A stack_template.toml or dependency group with unknown keys or values of the wrong type.
+    /// `path` is the manifest being read, each error names the file of the stack or group it is in.
+    Schema {
+        path: PathBuf,
+        errors: Vec<SchemaError>,
+    },
+    /// A filesystem operation failed
+    Io { context: String, source: io::Error },
+    /// An external command could not be started or exited unsuccessfully
+    Command {
+        command: String,
+        status: Option<i32>,
+        stderr: String,
+    },
+    /// A Handlebars template failed to render
+    Render {
+        template: PathBuf,
+        source: Box<handlebars::RenderError>,
+    },
+    /// A build step failed, keeps the category of the underlying error
+    Step {
+        step: &'static str,
+        source: Box<Error>,
+    },
+}
+
+impl Error {
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Error::Config(_) => 7,
+            Error::TemplateParse { .. } | Error::Schema { .. } => 3,
+            Error::Io { .. } => 4,
+            Error::Command { .. } => 5,
+            Error::Render { .. } => 6,
+            Error::Step { source, .. } => source.exit_code(),
+        }
+    }
+
+    pub fn template_parse(path: impl Into<PathBuf>, message: impl Into<String>) -> Self {
+        Error::TemplateParse {
+            path: path.into(),
+            message: message.into(),
+        }
+    }
+
+    /// Prints the error and every underlying cause to stderr
+    pub fn report(&self) {
+        eprintln!("error: {}", self);
+        let mut source = std::error::Error::source(self);
+        while let Some(cause) = source {
+            eprintln!("  caused by: {}", cause);
+            source = cause.source();
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Config(message) => write!(f, "{}", message),
+            Error::TemplateParse { path, message } => {
+                write!(f, "Invalid template {}: {}", path.display(), message)
+            }
+            Error::Schema { path, errors } => {
+                write!(
+                    f,
+                    "Invalid template {}, found {} error(s):",
+                    path.display(),
+                    errors.len()
+                )?;
+                for error in errors {
+                    write!(
+                        f,
+                        "\n  {}:{}:{}: {}",
+                        error.path.display(),
+                        error.line,
+                        error.column,
+                        error.message
+                    )?;
+                }
+                Ok(())
+            }
+            Error::Io { context, .. } => write!(f, "{}", context),
+            Error::Command {
+                command,
+                status,
+                stderr,
+            } => {
+                match status {
+                    Some(code) => write!(f, "Command `{}` exited with status {}", command, code)?,
+                    None => write!(f, "Command `{}` failed", command)?,
+                }
+                if !stderr.trim().is_empty() {
+                    write!(f, "\n{}", stderr.trim_end())?;
+                }
+                Ok(())
+            }
+            Error::Render { template, .. } => {
+                write!(f, "Failed to render template {}", template.display())
+            }
+            Error::Step { step, .. } => write!(f, "Build failed while {}", step),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io { source, .. } => Some(source),
+            Error::Render { source, .. } => Some(source.as_ref()),
+            Error::Step { source, .. } => Some(source.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+/// Adds a human readable description of what was being attempted to an io error
+pub trait Context<T> {
+    fn context(self, context: impl Into<String>) -> Result<T>;
+
+    fn with_context<F: FnOnce() -> String>(self, context: F) -> Result<T>;
+}
+
+impl<T> Context<T> for io::Result<T> {
+    fn context(self, context: impl Into<String>) -> Result<T> {
+        self.map_err(|source| Error::Io {
+            context: context.into(),
+            source,
+        })
+    }
+
+    fn with_context<F: FnOnce() -> String>(self, context: F) -> Result<T> {
+        self.map_err(|source| Error::Io {
+            context: context(),
+            source,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::*;
+    use crate::cli::Cli;
+
+    #[test]
+    fn exit_codes_are_distinct_from_usage_errors() {
+        let usage = Cli::try_parse_from(["germinate", "new", "--no-such-flag"]).unwrap_err();
+        let codes = [
+            Error::Config(String::new()).exit_code(),
+            Error::template_parse("stack_template.toml", "").exit_code(),
+            Error::Io {
+                context: String::new(),
+                source: io::Error::other(""),
+            }
+            .exit_code(),
+            Error::Command {
+                command: String::new(),
+                status: None,
+                stderr: String::new(),
+            }
+            .exit_code(),
+        ];
+        for (index, code) in codes.iter().enumerate() {
+            assert_ne!(*code, usage.exit_code());
+            assert!(!codes[..index].contains(code));
+        }
+    }
+}
@@ -0,0 +1,458 @@
+use std::{collections::BTreeMap, fmt};
+
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use toml::Value;
+
+/// Answers and built-in options an expression is evaluated against
+pub type Variables = BTreeMap<String, Value>;
+
+/// A parsed `when` condition, e.g. `db == "postgres" && !orm` or `"redis" in caches`. `and`, `or`
+/// and `not` can be used in place of `&&`, `||` and `!`, and strings may use single quotes.
+///
+/// A variable on its own is true when it is `true`, a non-empty string or array, or a non-zero
+/// integer. Variables that were never answered are false and equal nothing.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Variable(String),
+    Literal(Value),
+    Not(Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Eq(Box<Expr>, Box<Expr>),
+    NotEq(Box<Expr>, Box<Expr>),
+    In(Box<Expr>, Box<Expr>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Str(String),
+    Int(i64),
+    Not,
+    And,
+    Or,
+    Eq,
+    NotEq,
+    In,
+    Open,
+    Close,
+}
+
+impl Expr {
+    pub fn parse(source: &str) -> Result<Self, String> {
+        let tokens = tokenize(source)?;
+        let mut parser = Parser {
+            tokens: &tokens,
+            position: 0,
+        };
+        let expr = parser.parse_or()?;
+        match parser.peek() {
+            None => Ok(expr),
+            Some((column, token)) => Err(format!(
+                "unexpected {} at column {}",
+                describe(token),
+                column
+            )),
+        }
+    }
+
+    pub fn evaluate(&self, variables: &Variables) -> bool {
+        match self {
+            Expr::Not(expr) => !expr.evaluate(variables),
+            Expr::And(left, right) => left.evaluate(variables) && right.evaluate(variables),
+            Expr::Or(left, right) => left.evaluate(variables) || right.evaluate(variables),
+            Expr::Eq(left, right) => equals(left.value(variables), right.value(variables)),
+            Expr::NotEq(left, right) => !equals(left.value(variables), right.value(variables)),
+            Expr::In(item, list) => match (item.value(variables), list.value(variables)) {
+                (Some(item), Some(Value::Array(list))) => list.contains(item),
+                (Some(Value::String(item)), Some(Value::String(text))) => text.contains(item),
+                _ => false,
+            },
+            Expr::Variable(_) | Expr::Literal(_) => is_truthy(self.value(variables)),
+        }
+    }
+
+    /// Every variable the expression reads, in the order they appear
+    pub fn variables(&self) -> Vec<&str> {
+        match self {
+            Expr::Variable(name) => vec![name.as_str()],
+            Expr::Literal(_) => vec![],
+            Expr::Not(expr) => expr.variables(),
+            Expr::And(left, right)
+            | Expr::Or(left, right)
+            | Expr::Eq(left, right)
+            | Expr::NotEq(left, right)
+            | Expr::In(left, right) => {
+                let mut names = left.variables();
+                names.append(&mut right.variables());
+                names
+            }
+        }
+    }
+
+    fn value<'a>(&'a self, variables: &'a Variables) -> Option<&'a Value> {
+        match self {
+            Expr::Variable(name) => variables.get(name),
+            Expr::Literal(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Expr {
+    /// Writes the expression back out, with parentheses around every nested condition
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let nested = |f: &mut fmt::Formatter<'_>, expr: &Expr| match expr {
+            Expr::And(..) | Expr::Or(..) => write!(f, "({})", expr),
+            _ => write!(f, "{}", expr),
+        };
+        let binary = |f: &mut fmt::Formatter<'_>, left: &Expr, op: &str, right: &Expr| {
+            nested(f, left)?;
+            write!(f, " {} ", op)?;
+            nested(f, right)
+        };
+        match self {
+            Expr::Variable(name) => write!(f, "{}", name),
+            Expr::Literal(Value::String(value)) => {
+                // only the escapes `tokenize` reads back
+                let escaped = value
+                    .replace('\\', "\\\\")
+                    .replace('"', "\\\"")
+                    .replace('\n', "\\n")
+                    .replace('\t', "\\t");
+                write!(f, "\"{}\"", escaped)
+            }
+            Expr::Literal(value) => write!(f, "{}", value),
+            Expr::Not(expr) => {
+                write!(f, "!")?;
+                match expr.as_ref() {
+                    Expr::Variable(_) | Expr::Literal(_) | Expr::Not(_) => write!(f, "{}", expr),
+                    _ => write!(f, "({})", expr),
+                }
+            }
+            Expr::And(left, right) => binary(f, left, "&&", right),
+            Expr::Or(left, right) => binary(f, left, "||", right),
+            Expr::Eq(left, right) => binary(f, left, "==", right),
+            Expr::NotEq(left, right) => binary(f, left, "!=", right),
+            Expr::In(left, right) => binary(f, left, "in", right),
+        }
+    }
+}
+
+/// Conditions are written as their source, e.g. `when = "spa && !orm"`
+impl Serialize for Expr {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Expr {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let source = String::deserialize(deserializer)?;
+        Expr::parse(&source).map_err(|err| de::Error::custom(format!("invalid condition: {}", err)))
+    }
+}
+
+fn equals(left: Option<&Value>, right: Option<&Value>) -> bool {
+    matches!((left, right), (Some(left), Some(right)) if left == right)
+}
+
+fn is_truthy(value: Option<&Value>) -> bool {
+    match value {
+        Some(Value::Boolean(value)) => *value,
+        Some(Value::String(value)) => !value.is_empty(),
+        Some(Value::Integer(value)) => *value != 0,
+        Some(Value::Array(value)) => !value.is_empty(),
+        Some(_) => true,
+        None => false,
+    }
+}
+
+fn describe(token: &Token) -> String {
+    match token {
+        Token::Ident(name) => format!("`{}`", name),
+        Token::Str(value) => format!("\"{}\"", value),
+        Token::Int(value) => format!("`{}`", value),
+        Token::Not => "`!`".to_string(),
+        Token::And => "`&&`".to_string(),
+        Token::Or => "`||`".to_string(),
+        Token::Eq => "`==`".to_string(),
+        Token::NotEq => "`!=`".to_string(),
+        Token::In => "`in`".to_string(),
+        Token::Open => "`(`".to_string(),
+        Token::Close => "`)`".to_string(),
+    }
+}
+
+/// Splits an expression into tokens, each paired with its (1 based) column
+fn tokenize(source: &str) -> Result<Vec<(usize, Token)>, String> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = vec![];
+    let mut index = 0;
+    while index < chars.len() {
+        let column = index + 1;
+        let next = chars.get(index + 1).copied();
+        let token = match chars[index] {
+            c if c.is_whitespace() => {
+                index += 1;
+                continue;
+            }
+            '(' => Token::Open,
+            ')' => Token::Close,
+            '!' if next == Some('=') => Token::NotEq,
+            '!' => Token::Not,
+            '=' if next == Some('=') => Token::Eq,
+            '&' if next == Some('&') => Token::And,
+            '|' if next == Some('|') => Token::Or,
+            quote @ ('"' | '\'') => {
+                let unterminated = || format!("unterminated string at column {}", column);
+                let mut value = String::new();
+                let mut end = index + 1;
+                loop {
+                    match *chars.get(end).ok_or_else(unterminated)? {
+                        c if c == quote => break,
+                        // a backslash escapes the next character, `\n` and `\t` are a newline
+                        // and a tab
+                        '\\' => {
+                            value.push(match *chars.get(end + 1).ok_or_else(unterminated)? {
+                                'n' => '\n',
+                                't' => '\t',
+                                c => c,
+                            });
+                            end += 2;
+                        }
+                        c => {
+                            value.push(c);
+                            end += 1;
+                        }
+                    }
+                }
+                index = end + 1;
+                tokens.push((column, Token::Str(value)));
+                continue;
+            }
+            c if c.is_ascii_digit() || c == '-' => {
+                let end = chars[index + 1..]
+                    .iter()
+                    .position(|c| !c.is_ascii_digit())
+                    .map_or(chars.len(), |end| index + 1 + end);
+                let text: String = chars[index..end].iter().collect();
+                let value = text
+                    .parse()
+                    .map_err(|_| format!("invalid number `{}` at column {}", text, column))?;
+                index = end;
+                tokens.push((column, Token::Int(value)));
+                continue;
+            }
+            c if c.is_ascii_alphabetic() || c == '_' => {
+                let end = chars[index..]
+                    .iter()
+                    .position(|c| !(c.is_ascii_alphanumeric() || *c == '_'))
+                    .map_or(chars.len(), |end| index + end);
+                let word: String = chars[index..end].iter().collect();
+                index = end;
+                let token = match word.as_str() {
+                    "in" => Token::In,
+                    "and" => Token::And,
+                    "or" => Token::Or,
+                    "not" => Token::Not,
+                    _ => Token::Ident(word),
+                };
+                tokens.push((column, token));
+                continue;
+            }
+            c => return Err(format!("unexpected `{}` at column {}", c, column)),
+        };
+        index += match token {
+            Token::NotEq | Token::Eq | Token::And | Token::Or => 2,
+            _ => 1,
+        };
+        tokens.push((column, token));
+    }
+    Ok(tokens)
+}
+
+struct Parser<'a> {
+    tokens: &'a [(usize, Token)],
+    position: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<&'a (usize, Token)> {
+        self.tokens.get(self.position)
+    }
+
+    fn next_if(&mut self, token: &Token) -> bool {
+        if self.peek().is_some_and(|(_, next)| next == token) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn parse_or(&mut self) -> Result<Expr, String> {
+        let mut expr = self.parse_and()?;
+        while self.next_if(&Token::Or) {
+            expr = Expr::Or(Box::new(expr), Box::new(self.parse_and()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_and(&mut self) -> Result<Expr, String> {
+        let mut expr = self.parse_unary()?;
+        while self.next_if(&Token::And) {
+            expr = Expr::And(Box::new(expr), Box::new(self.parse_unary()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr, String> {
+        if self.next_if(&Token::Not) {
+            return Ok(Expr::Not(Box::new(self.parse_unary()?)));
+        }
+        if self.next_if(&Token::Open) {
+            let expr = self.parse_or()?;
+            if !self.next_if(&Token::Close) {
+                return Err(self.expected("`)`"));
+            }
+            return Ok(expr);
+        }
+        let left = self.parse_operand()?;
+        let compare = match self.peek() {
+            Some((_, Token::Eq)) => Expr::Eq,
+            Some((_, Token::NotEq)) => Expr::NotEq,
+            Some((_, Token::In)) => Expr::In,
+            _ => return Ok(left),
+        };
+        self.position += 1;
+        Ok(compare(Box::new(left), Box::new(self.parse_operand()?)))
+    }
+
+    fn parse_operand(&mut self) -> Result<Expr, String> {
+        let operand = match self.peek() {
+            Some((_, Token::Ident(name))) if name == "true" => Expr::Literal(Value::Boolean(true)),
+            Some((_, Token::Ident(name))) if name == "false" => {
+                Expr::Literal(Value::Boolean(false))
+            }
+            Some((_, Token::Ident(name))) => Expr::Variable(name.clone()),
+            Some((_, Token::Str(value))) => Expr::Literal(Value::String(value.clone())),
+            Some((_, Token::Int(value))) => Expr::Literal(Value::Integer(*value)),
+            _ => return Err(self.expected("a name or value")),
+        };
+        self.position += 1;
+        Ok(operand)
+    }
+
+    fn expected(&self, what: &str) -> String {
+        match self.peek() {
+            Some((column, token)) => format!(
+                "expected {} at column {}, found {}",
+                what,
+                column,
+                describe(token)
+            ),
+            None => format!("expected {} at the end of the expression", what),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn variables() -> Variables {
+        let mut variables = Variables::new();
+        variables.insert("db".to_string(), Value::String("postgres".to_string()));
+        variables.insert("orm".to_string(), Value::Boolean(false));
+        variables.insert("workers".to_string(), Value::Integer(2));
+        variables.insert(
+            "caches".to_string(),
+            Value::Array(vec![Value::String("redis".to_string())]),
+        );
+        variables
+    }
+
+    #[test]
+    fn evaluate_expressions() {
+        let cases = [
+            ("db == \"postgres\" && !orm", true),
+            ("db != \"postgres\" || orm", false),
+            ("\"redis\" in caches && workers == 2", true),
+            ("!(\"memcached\" in caches)", true),
+            ("missing || missing == \"x\"", false),
+            ("workers && orm == false", true),
+            ("not orm and (db == \"sqlite\" or workers == 2)", true),
+            ("db == 'postgres' and \"redis\" in caches", true),
+        ];
+        for (source, expected) in cases {
+            let expr = Expr::parse(source).expect(source);
+            assert_eq!(expr.evaluate(&variables()), expected, "{}", source);
+        }
+    }
+
+    #[test]
+    fn report_syntax_errors() {
+        assert_eq!(
+            Expr::parse("db == ").unwrap_err(),
+            "expected a name or value at the end of the expression"
+        );
+        assert_eq!(
+            Expr::parse("(orm && db").unwrap_err(),
+            "expected `)` at the end of the expression"
+        );
+        assert_eq!(
+            Expr::parse("orm db").unwrap_err(),
+            "unexpected `db` at column 5"
+        );
+        assert_eq!(
+            Expr::parse("db = \"x\"").unwrap_err(),
+            "unexpected `=` at column 4"
+        );
+    }
+
+    #[test]
+    fn display_expressions() {
+        let expr = Expr::parse("not orm and (db == \"sqlite\" or 2 in workers)").unwrap();
+        assert_eq!(
+            expr.to_string(),
+            "!orm && (db == \"sqlite\" || 2 in workers)"
+        );
+    }
+
+    #[test]
+    fn round_trip_string_literals() {
+        let expr =
+            Expr::parse(r#"db == "say \"hi\"" || db == 'C:\\temp\n' || db == 'it\'s'"#).unwrap();
+        assert_eq!(
+            expr,
+            Expr::Or(
+                Box::new(Expr::Or(
+                    Box::new(Expr::Eq(
+                        Box::new(Expr::Variable("db".to_string())),
+                        Box::new(Expr::Literal(Value::String("say \"hi\"".to_string())))
+                    )),
+                    Box::new(Expr::Eq(
+                        Box::new(Expr::Variable("db".to_string())),
+                        Box::new(Expr::Literal(Value::String("C:\\temp\n".to_string())))
+                    ))
+                )),
+                Box::new(Expr::Eq(
+                    Box::new(Expr::Variable("db".to_string())),
+                    Box::new(Expr::Literal(Value::String("it's".to_string())))
+                ))
+            )
+        );
+        assert_eq!(Expr::parse(&expr.to_string()).unwrap(), expr);
+        assert_eq!(
+            Expr::parse("db == \"open\\\"").unwrap_err(),
+            "unterminated string at column 7"
+        );
+    }
+
+    #[test]
+    fn list_variables() {
+        let expr = Expr::parse("db == \"sqlite\" || (\"a\" in caches && !orm)").unwrap();
+        assert_eq!(expr.variables(), ["db", "caches", "orm"]);
+    }
+}
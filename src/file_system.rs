@@ -1,6 +1,14 @@
-use std::{fs, io, path::Path};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
 
-pub fn copy_dir_all(src: impl AsRef<Path>, dst: impl AsRef<Path>) -> io::Result<()> {
+/// Recursively copies `src` into `dst`, leaving any destination file listed in `keep` untouched
+pub fn copy_dir_except(
+    src: impl AsRef<Path>,
+    dst: impl AsRef<Path>,
+    keep: &[PathBuf],
+) -> io::Result<()> {
     println!("->> Copying dir: {:?} to {:?}", src.as_ref(), dst.as_ref());
     fs::create_dir_all(&dst)?;
     for entry in fs::read_dir(src)? {
@@ -8,7 +16,9 @@ pub fn copy_dir_all(src: impl AsRef<Path>, dst: impl AsRef<Path>) -> io::Result<
         let ty = entry.file_type()?;
         let dst_path = dst.as_ref().join(entry.file_name());
         if ty.is_dir() {
-            copy_dir_all(entry.path(), &dst_path)?;
+            copy_dir_except(entry.path(), &dst_path, keep)?;
+        } else if keep.contains(&dst_path) {
+            println!("->> Keeping existing file: {:?}", dst_path);
         } else {
             println!("->> Copying file: {:?} to {:?}", entry.path(), dst_path);
             fs::copy(entry.path(), &dst_path)?;
@@ -16,3 +26,62 @@ pub fn copy_dir_all(src: impl AsRef<Path>, dst: impl AsRef<Path>) -> io::Result<
     }
     Ok(())
 }
+
+/// Lists every file `copy_dir_except` would copy out of `src`, relative to `src`
+pub fn list_files(src: impl AsRef<Path>) -> io::Result<Vec<PathBuf>> {
+    let mut files = vec![];
+    collect_files(src.as_ref(), Path::new(""), &mut files)?;
+    files.sort();
+    Ok(files)
+}
+
+fn collect_files(src: &Path, prefix: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in fs::read_dir(src)? {
+        let entry = entry?;
+        let relative = prefix.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            collect_files(&entry.path(), &relative, files)?;
+        } else {
+            files.push(relative);
+        }
+    }
+    Ok(())
+}
+
+/// Lists every file, folder and symlink inside `dir`, each folder before its contents. Symlinks
+/// are listed but not followed.
+pub fn list_entries(dir: impl AsRef<Path>) -> io::Result<Vec<PathBuf>> {
+    let mut entries = vec![];
+    collect_entries(dir.as_ref(), &mut entries)?;
+    Ok(entries)
+}
+
+fn collect_entries(dir: &Path, entries: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        entries.push(entry.path());
+        if entry.file_type()?.is_dir() {
+            collect_entries(&entry.path(), entries)?;
+        }
+    }
+    Ok(())
+}
+
+/// Lists the files in `subfolder` across every layer of an extended stack, relative to the
+/// subfolder, paired with the layer they are taken from. A later layer replaces a file of an
+/// earlier one.
+pub fn list_layered_files(
+    layers: &[PathBuf],
+    subfolder: impl AsRef<Path>,
+) -> Vec<(PathBuf, PathBuf)> {
+    let mut files: Vec<(PathBuf, PathBuf)> = vec![];
+    for layer in layers {
+        let src = layer.join(subfolder.as_ref());
+        for file in list_files(&src).unwrap_or_default() {
+            files.retain(|(existing, _)| *existing != file);
+            files.push((file, src.clone()));
+        }
+    }
+    files.sort();
+    files
+}
@@ -0,0 +1,434 @@
+use std::path::{Path, PathBuf};
+
+use crate::{
+    addons::Addon,
+    config::{Language, PackageManager},
+    container,
+    error::Result,
+    file_system,
+    module::Module,
+    prompts::Prompt,
+    stack::{self, Stack},
+    version,
+};
+
+const PACKAGE_MANAGERS: [&str; 2] = ["cargo", "npm"];
+
+pub fn list_stacks(templates_dir: &Path) -> Result<()> {
+    stack_list(templates_dir)?
+        .iter()
+        .for_each(|line| println!("{}", line));
+    Ok(())
+}
+
+pub fn show_stack(name: &str, templates_dir: &Path) -> Result<()> {
+    let stack = stack::find(templates_dir, name)?;
+    let mut lines = vec![];
+    describe_stack(&mut lines, &stack);
+    lines.iter().for_each(|line| println!("{}", line));
+    Ok(())
+}
+
+/// The lines of `germinate list`: every stack in list order, then the invalid ones
+fn stack_list(templates_dir: &Path) -> Result<Vec<String>> {
+    let mut lines = vec![];
+    let discovered = stack::discover(templates_dir)?;
+    if discovered.stacks.is_empty() && discovered.invalid.is_empty() {
+        lines.push(format!("No stacks found in {}", templates_dir.display()));
+    }
+    let width = discovered
+        .stacks
+        .iter()
+        .map(|stack| stack.name.len())
+        .chain(discovered.invalid.iter().map(|(name, _)| name.len()))
+        .max()
+        .unwrap_or(0)
+        + 2;
+    for stack in &discovered.stacks {
+        lines.push(format!(
+            "{:<width$}{:<22}{}",
+            stack.name,
+            stack.label,
+            or_none(&stack.description, "no description"),
+            width = width
+        ));
+    }
+    if !discovered.invalid.is_empty() {
+        lines.push(
+            "\nInvalid stacks, check them with `germinate template validate <stack>`:".to_string(),
+        );
+        for (name, err) in &discovered.invalid {
+            let reason = err.to_string();
+            lines.push(format!(
+                "{:<width$}{}",
+                name,
+                reason.lines().next().unwrap_or_default(),
+                width = width
+            ));
+        }
+    }
+    Ok(lines)
+}
+
+/// The lines of `germinate show`
+fn describe_stack(lines: &mut Vec<String>, stack: &Stack) {
+    let template_dir = &stack.template_dir;
+    let template = &stack.template;
+
+    lines.push(format!("{} ({})", stack.label, stack.name));
+    lines.push(or_none(&stack.description, "no description").to_string());
+    lines.push(format!("Template directory: {}", template_dir.display()));
+    if stack.template_dirs.len() > 1 {
+        let chain = stack
+            .template_dirs
+            .iter()
+            .rev()
+            .skip(1)
+            .map(|dir| layer_name(dir))
+            .collect::<Vec<_>>();
+        lines.push(format!("Extends: {}", chain.join(" -> ")));
+    }
+
+    lines.push(format!(
+        "\nLanguages: {}",
+        join_names(&stack.languages, Language::get_name)
+    ));
+    lines.push(format!(
+        "Package managers: {}",
+        join_names(&stack.package_managers, PackageManager::get_name)
+    ));
+    let linters = stack
+        .linters
+        .iter()
+        .map(|spec| match &spec.when {
+            Some(when) => format!("{} (when {})", spec.linter.get_name(), when),
+            None => spec.linter.get_name().to_string(),
+        })
+        .collect::<Vec<_>>();
+    lines.push(format!("Linters: {}", or_none(&linters.join(", "), "none")));
+
+    lines.push("\nDependencies:".to_string());
+    if !template.get_groups().is_empty() {
+        lines.push(format!("  groups: {}", template.get_groups().join(", ")));
+    }
+    for manager in PACKAGE_MANAGERS {
+        if let Some(Some(deps)) = template.get_dependencies().get(manager) {
+            lines.push(format!("  {}:", manager));
+            deps.iter().for_each(|dep| describe_dep(lines, dep, 4));
+        }
+    }
+
+    lines.push("\nScripts:".to_string());
+    match template.get_scripts() {
+        Some(scripts) => {
+            for manager in PACKAGE_MANAGERS {
+                if let Some(scripts) = scripts.get(manager) {
+                    lines.push(format!("  {}:", manager));
+                    let mut names = scripts.keys().collect::<Vec<_>>();
+                    names.sort();
+                    for name in names {
+                        lines.push(format!("    {} = {}", name, scripts[name]));
+                    }
+                }
+            }
+        }
+        None => lines.push("  none".to_string()),
+    }
+
+    lines.push("\nPrompts:".to_string());
+    if stack.prompts.is_empty() {
+        lines.push("  none".to_string());
+    }
+    stack
+        .prompts
+        .iter()
+        .for_each(|prompt| describe_prompt(lines, prompt));
+
+    lines.push("\nAddons:".to_string());
+    if stack.addons.is_empty() {
+        lines.push("  none".to_string());
+    }
+    for addon in &stack.addons {
+        describe_addon(lines, stack, addon);
+    }
+
+    lines.push("\nSubfolders:".to_string());
+    match template.get_subfolders() {
+        Some(subfolders) => subfolders
+            .iter()
+            .for_each(|folder| lines.push(format!("  {}", folder.display()))),
+        None => lines.push("  none".to_string()),
+    }
+
+    lines.push("\nFiles copied before install:".to_string());
+    describe_layered_files(lines, stack, "before_install");
+    lines.push("\nFiles copied after install:".to_string());
+    describe_layered_files(lines, stack, "after_install");
+    lines.push("\nFiles generated when containers are enabled:".to_string());
+    describe_files(lines, container::get_docker_files(&stack.template_dirs));
+}
+
+fn join_names<T>(values: &[T], get_name: fn(&T) -> &'static str) -> String {
+    let names = values.iter().map(get_name).collect::<Vec<_>>();
+    or_none(&names.join(", "), "none").to_string()
+}
+
+fn or_none<'a>(value: &'a str, fallback: &'a str) -> &'a str {
+    if value.is_empty() {
+        fallback
+    } else {
+        value
+    }
+}
+
+fn describe_dep(lines: &mut Vec<String>, dep: &Module, indent: usize) {
+    let mut line = format!("{:indent$}{}", "", dep.name, indent = indent);
+    if dep.version != version::LATEST {
+        line.push_str(&format!("@{}", dep.version));
+    }
+    if let Some(package) = &dep.package {
+        line.push_str(&format!(" (package {})", package));
+    }
+    if let Some(rename) = &dep.rename {
+        line.push_str(&format!(" (as {})", rename));
+    }
+    if dep.dev {
+        line.push_str(" (dev)");
+    }
+    if dep.build {
+        line.push_str(" (build)");
+    }
+    if dep.peer {
+        line.push_str(" (peer)");
+    }
+    if dep.optional {
+        line.push_str(" (optional)");
+    }
+    if dep.exact {
+        line.push_str(" (exact)");
+    }
+    if let Some(features) = &dep.features {
+        line.push_str(&format!(" [features: {}]", features.join(", ")));
+    }
+    if !dep.default_features {
+        line.push_str(" [no default features]");
+    }
+    lines.push(line);
+
+    if let Some(git) = &dep.git {
+        let reference = [&dep.branch, &dep.tag, &dep.rev]
+            .into_iter()
+            .flatten()
+            .next();
+        match reference {
+            Some(reference) => lines.push(format!(
+                "{:indent$}  git: {} ({})",
+                "",
+                git,
+                reference,
+                indent = indent
+            )),
+            None => lines.push(format!("{:indent$}  git: {}", "", git, indent = indent)),
+        }
+    }
+    if let Some(alias) = &dep.alias {
+        lines.push(format!("{:indent$}  alias: {}", "", alias, indent = indent));
+    }
+    if let Some(tarball) = &dep.tarball {
+        lines.push(format!(
+            "{:indent$}  tarball: {}",
+            "",
+            tarball,
+            indent = indent
+        ));
+    }
+    if let Some(file) = &dep.file {
+        lines.push(format!("{:indent$}  file: {}", "", file, indent = indent));
+    }
+    if let Some(registry) = &dep.registry {
+        lines.push(format!(
+            "{:indent$}  registry: {}",
+            "",
+            registry,
+            indent = indent
+        ));
+    }
+    if let Some(path) = &dep.path {
+        lines.push(format!("{:indent$}  path: {}", "", path, indent = indent));
+    }
+    if let Some(target) = &dep.target {
+        lines.push(format!(
+            "{:indent$}  target: {}",
+            "",
+            target,
+            indent = indent
+        ));
+    }
+
+    if let Some(when) = &dep.when {
+        lines.push(format!("{:indent$}  when: {}", "", when, indent = indent));
+    }
+    if let Some(then) = &dep.then {
+        for cmd in then {
+            lines.push(format!(
+                "{:indent$}  then: {}",
+                "",
+                cmd.join(" "),
+                indent = indent
+            ));
+        }
+    }
+}
+
+fn describe_prompt(lines: &mut Vec<String>, prompt: &Prompt) {
+    let mut line = format!(
+        "  {} ({}): {}",
+        prompt.name,
+        prompt.kind.get_name(),
+        prompt.message
+    );
+    if let Some(default) = &prompt.default {
+        line.push_str(&format!(" [default: {}]", default));
+    }
+    if !prompt.options.is_empty() {
+        line.push_str(&format!(" [options: {}]", prompt.options.join(", ")));
+    }
+    lines.push(line);
+    if let Some(when) = &prompt.when {
+        lines.push(format!("    when: {}", when));
+    }
+}
+
+fn describe_addon(lines: &mut Vec<String>, stack: &Stack, addon: &Addon) {
+    lines.push(format!("  {}: {}", addon.name, addon.get_item()));
+    for (manager, deps) in [("npm", &addon.npm_deps), ("cargo", &addon.cargo_deps)] {
+        if !deps.is_empty() {
+            lines.push(format!("    {} deps:", manager));
+            deps.iter().for_each(|dep| describe_dep(lines, dep, 6));
+        }
+    }
+    for manager in PACKAGE_MANAGERS {
+        if let Some(scripts) = addon.scripts.get(manager) {
+            let mut names = scripts.keys().collect::<Vec<_>>();
+            names.sort();
+            for name in names {
+                lines.push(format!(
+                    "    {} script: {} = {}",
+                    manager, name, scripts[name]
+                ));
+            }
+        }
+    }
+    let files = file_system::list_layered_files(&stack.template_dirs, &addon.files);
+    if !files.is_empty() {
+        lines.push(format!(
+            "    files: {} ({} files)",
+            addon.files.display(),
+            files.len()
+        ));
+    }
+    for cmd in &addon.then {
+        lines.push(format!("    then: {}", cmd.join(" ")));
+    }
+}
+
+/// Lists the files of `subfolder`, naming the extended stack a file is inherited from
+fn describe_layered_files(lines: &mut Vec<String>, stack: &Stack, subfolder: &str) {
+    let files = file_system::list_layered_files(&stack.template_dirs, subfolder);
+    if files.is_empty() {
+        lines.push("  none".to_string());
+    }
+    for (file, src) in files {
+        let layer = stack
+            .template_dirs
+            .iter()
+            .find(|dir| dir.join(subfolder) == src);
+        match layer {
+            Some(dir) if *dir != stack.template_dir => {
+                lines.push(format!("  {} (from {})", file.display(), layer_name(dir)))
+            }
+            _ => lines.push(format!("  {}", file.display())),
+        }
+    }
+}
+
+fn layer_name(template_dir: &Path) -> String {
+    template_dir
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string()
+}
+
+fn describe_files(lines: &mut Vec<String>, files: Vec<PathBuf>) {
+    if files.is_empty() {
+        lines.push("  none".to_string());
+    }
+    files
+        .iter()
+        .for_each(|file| lines.push(format!("  {}", file.display())));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEMPLATES_DIR: &str = "test/__mocks__/extends";
+
+    #[test]
+    fn list_stacks_in_order_and_invalid_ones_apart() {
+        let lines = stack_list(Path::new(TEMPLATES_DIR)).expect("Error listing stacks");
+        assert_eq!(
+            lines,
+            [
+                "grouped  grouped               Hono with validation",
+                "base     base                  no description",
+                "child    child                 no description",
+                "\nInvalid stacks, check them with `germinate template validate <stack>`:",
+                "broken   Invalid template test/__mocks__/extends/broken/stack_template.toml: `extends` names unknown stack `missing`",
+            ]
+        );
+    }
+
+    #[test]
+    fn show_extending_stack() {
+        let stack = stack::find(Path::new(TEMPLATES_DIR), "child").expect("Error finding stack");
+        let mut lines = vec![];
+        describe_stack(&mut lines, &stack);
+
+        let section = |title: &str| {
+            let start = lines
+                .iter()
+                .position(|line| line == title)
+                .unwrap_or_else(|| panic!("No {} section", title));
+            lines[start + 1..]
+                .iter()
+                .take_while(|line| !line.starts_with('\n'))
+                .cloned()
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(lines[0], "child (child)");
+        assert!(lines.contains(&"Extends: base".to_string()));
+        assert_eq!(
+            section("\nDependencies:"),
+            [
+                "  npm:",
+                "    hono@^4.0.0",
+                "    typescript (dev)",
+                "    drizzle-orm"
+            ]
+        );
+        assert_eq!(
+            section("\nScripts:"),
+            [
+                "  npm:",
+                "    start = bun run src/index.ts",
+                "    test = bun test"
+            ]
+        );
+        assert_eq!(
+            section("\nFiles copied before install:"),
+            ["  NOTES.md (from base)"]
+        );
+    }
+}
@@ -1,23 +1,44 @@
 use std::process::Command;
 
-#[derive(Debug, Clone)]
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::expression::Expr;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
 pub enum Linter {
     ESLint,
     Stylelint,
     Clippy,
 }
 
+/// A linter declared by a stack, installed when its `when` condition holds
+#[derive(Debug, Clone)]
+pub struct LinterSpec {
+    pub linter: Linter,
+    pub when: Option<Expr>,
+}
+
 impl Linter {
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            Linter::ESLint => "eslint",
+            Linter::Stylelint => "stylelint",
+            Linter::Clippy => "clippy",
+        }
+    }
+
     pub fn get_install_commands(&self) -> Vec<Command> {
         match self {
             Linter::ESLint => {
                 let mut command = Command::new("bun");
-                command.args(&["add", "eslint", "--dev"]);
+                command.args(["add", "eslint", "--dev"]);
                 vec![command]
             }
             Linter::Stylelint => {
                 let mut command = Command::new("bun");
-                command.args(&["add", "stylelint", "--dev"]);
+                command.args(["add", "stylelint", "--dev"]);
                 vec![command]
             }
             Linter::Clippy => {
@@ -28,7 +49,7 @@ impl Linter {
                 commands.push(command);
 
                 let mut command = Command::new("rustup");
-                command.args(&["component", "add", "clippy"]);
+                command.args(["component", "add", "clippy"]);
                 commands.push(command);
 
                 commands
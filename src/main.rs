@@ -1,22 +1,100 @@
+mod addons;
+mod answers;
 mod builder;
+mod cli;
+mod completions;
 mod config;
 mod container;
 mod db_client;
 mod dialogue;
+mod error;
+mod expression;
 mod file_system;
+mod inspect;
 mod linters;
+mod manifest;
 mod module;
+mod plan;
+mod prompts;
+mod schema;
+mod stack;
+mod stack_generator;
 mod template_generator;
+mod templates;
 mod toml_parser;
+mod validate;
+mod version;
 
-use crate::{builder::ProjectBuilder, config::ScaffoldConfig};
+use clap::{CommandFactory, Parser};
+use clap_complete::CompleteEnv;
+
+use crate::{
+    answers::Answers,
+    builder::{BuildOptions, ExistingDir, ProjectBuilder},
+    cli::{Cli, Commands, TemplateCommands},
+    config::ScaffoldConfig,
+    error::Result,
+};
 
 fn main() {
-    let user_config = dialogue::get_user_config().unwrap();
-    let app_config = ScaffoldConfig::new(user_config);
-    ProjectBuilder::build(&app_config);
-    //?  Can we parallelize it? (future optimization, but keep thinks modularized with a mind towards this end)
+    // answers the shell when it asks for completions, then exits
+    CompleteEnv::with_factory(Cli::command)
+        .var(completions::COMPLETE_ENV)
+        .complete();
+    if let Err(err) = run() {
+        err.report();
+        std::process::exit(err.exit_code());
+    }
+}
 
-    // return success/errors
-    //? Collect success from the scaffold engine and return it to the user
+fn run() -> Result<()> {
+    let cli = Cli::parse();
+    let command = cli
+        .command
+        .unwrap_or_else(|| Commands::New(Default::default()));
+
+    let templates_dir = || templates::resolve_templates_dir(cli.templates_dir.as_deref());
+
+    match command {
+        Commands::New(mut args) => {
+            let templates_dir = templates_dir()?;
+            if let Some(path) = args.answers.clone() {
+                Answers::load(&path)?.apply_to(&mut args);
+            }
+            let user_config = dialogue::get_user_config(&args, &templates_dir)?;
+            let answers = Answers::from(&user_config);
+            let app_config = ScaffoldConfig::new(user_config)?;
+            let existing = if args.force {
+                ExistingDir::Force
+            } else if args.merge {
+                ExistingDir::Merge
+            } else {
+                ExistingDir::Abort
+            };
+            if args.dry_run {
+                plan::print_plan(&app_config, existing, args.save_answers.as_deref())
+            } else {
+                if let Some(path) = &args.save_answers {
+                    answers.save(path)?;
+                    println!("->> Answers saved to {}", path.display());
+                }
+                let options = BuildOptions {
+                    keep_on_failure: args.keep_on_failure,
+                    existing,
+                };
+                ProjectBuilder::build(&app_config, &options)
+            }
+        }
+        Commands::List => inspect::list_stacks(&templates_dir()?),
+        Commands::Show { stack } => inspect::show_stack(&stack, &templates_dir()?),
+        Commands::Completions { shell } => completions::print_completions(shell),
+        Commands::Template { command } => match command {
+            TemplateCommands::Validate { stack } => validate::validate_stack(&stack, templates_dir),
+            TemplateCommands::New { name } => {
+                stack_generator::new_stack(&name, &templates_dir()?).map(|_| ())
+            }
+        },
+        Commands::Man { out_dir } => completions::print_man(out_dir.as_deref()),
+    }
+    //?  Can we parallelize it? (future optimization, but keep thinks modularized with a mind towards this end)
 }
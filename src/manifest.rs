@@ -0,0 +1,530 @@
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+};
+
+use indexmap::IndexMap;
+use schemars::{gen::SchemaGenerator, schema::Schema, JsonSchema};
+use serde::{
+    de::{self, value::MapAccessDeserializer, value::SeqAccessDeserializer, MapAccess, SeqAccess},
+    Deserialize, Deserializer, Serialize,
+};
+use toml::Value;
+
+use crate::{
+    config::{Language, PackageManager},
+    linters::Linter,
+    module::{self, Module, ThenCommands},
+    prompts::PromptKind,
+};
+
+/// A stack's `stack_template.toml` as written, before it is merged with the stacks it extends
+/// and the groups it uses. Serializes back to an equivalent manifest. The schema manifests are
+/// checked against is generated from these types.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct Manifest {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub order: Option<i64>,
+    /// Defaults to false, `None` lets an extending stack keep the value of the one it extends
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub web: Option<bool>,
+    /// Stack whose manifest this one is merged over
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extends: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub remove_deps: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub remove_scripts: Vec<String>,
+    /// Dependency groups from the templates' `_groups` folder
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub groups: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub languages: Option<Vec<Language>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub package_managers: Option<Vec<PackageManager>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub linters: Option<Linters>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub subfolders: Option<Subfolders>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scripts: Option<ScriptTables>,
+    /// Required once the stacks it extends and its groups are merged in
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deps: Option<DepTables>,
+    /// Parsed in order by `Prompt::parse`, since each may refer to the earlier ones
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    pub prompts: IndexMap<String, PromptEntry>,
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    pub addons: IndexMap<String, AddonEntry>,
+}
+
+/// A dependency group file, `_groups/<group>.toml`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct Group {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub deps: DepTables,
+}
+
+/// The `[[deps.<package manager>]]` entries of a manifest, group or addon
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct DepTables {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub npm: Option<Vec<Module>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cargo: Option<Vec<Module>>,
+}
+
+/// The `[scripts.<package manager>]` tables of a manifest or addon, script names to commands
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ScriptTables {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub npm: Option<IndexMap<String, String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cargo: Option<IndexMap<String, String>>,
+}
+
+/// Folder tree created in every project, `{}` marks an empty folder
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Subfolders(pub IndexMap<String, Subfolders>);
+
+/// A `[prompts.<name>]` table, checked by `Prompt::parse` once the manifests are merged
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct PromptEntry {
+    #[serde(rename = "type", default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<PromptKind>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub help: Option<String>,
+    /// Checked against the prompt's type, which the schema can't do
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(schema_with = "any_value")]
+    pub default: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub options: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub when: Option<String>,
+}
+
+/// Either `linters = ["clippy"]` or a table of linters with optional `when` conditions
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+#[serde(untagged)]
+pub enum Linters {
+    Names(Vec<Linter>),
+    Conditional(IndexMap<Linter, LinterEntry>),
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct LinterEntry {
+    /// Checked against the stack's prompts once they are parsed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub when: Option<String>,
+}
+
+/// An `[addons.<name>]` table
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct AddonEntry {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Defaults to `addons/<name>`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub files: Option<PathBuf>,
+    #[serde(
+        default,
+        deserialize_with = "module::deserialize_then",
+        skip_serializing_if = "Option::is_none"
+    )]
+    #[schemars(with = "Option<ThenCommands>")]
+    pub then: Option<ThenCommands>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deps: Option<DepTables>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scripts: Option<ScriptTables>,
+}
+
+impl Manifest {
+    /// Merges a manifest over the one it extends. Tables are merged key by key and deps are
+    /// matched by name, anything else in the child replaces the parent's value. The stack's
+    /// identity (title, label, description, order) is never inherited.
+    pub fn merge(mut parent: Manifest, child: Manifest) -> Result<Manifest, String> {
+        parent.remove_deps(&child.remove_deps)?;
+        parent.remove_scripts(&child.remove_scripts)?;
+        for group in child.groups {
+            if !parent.groups.contains(&group) {
+                parent.groups.push(group);
+            }
+        }
+        for (name, prompt) in child.prompts {
+            match parent.prompts.get_mut(&name) {
+                Some(parent_prompt) => parent_prompt.merge(prompt),
+                None => {
+                    parent.prompts.insert(name, prompt);
+                }
+            }
+        }
+        for (name, addon) in child.addons {
+            match parent.addons.get_mut(&name) {
+                Some(parent_addon) => parent_addon.merge(addon),
+                None => {
+                    parent.addons.insert(name, addon);
+                }
+            }
+        }
+
+        Ok(Manifest {
+            title: child.title,
+            label: child.label,
+            description: child.description,
+            order: child.order,
+            web: child.web.or(parent.web),
+            extends: child.extends,
+            remove_deps: vec![],
+            remove_scripts: vec![],
+            groups: parent.groups,
+            languages: child.languages.or(parent.languages),
+            package_managers: child.package_managers.or(parent.package_managers),
+            linters: merge_with(parent.linters, child.linters, Linters::merge),
+            subfolders: merge_with(parent.subfolders, child.subfolders, Subfolders::merge),
+            scripts: merge_with(parent.scripts, child.scripts, ScriptTables::merge),
+            deps: merge_with(parent.deps, child.deps, DepTables::merge),
+            prompts: parent.prompts,
+            addons: parent.addons,
+        })
+    }
+
+    fn remove_deps(&mut self, names: &[String]) -> Result<(), String> {
+        let mut removed = vec![];
+        if let Some(deps) = &mut self.deps {
+            for entries in [&mut deps.npm, &mut deps.cargo].into_iter().flatten() {
+                entries.retain(|entry| match names.contains(&entry.name) {
+                    true => {
+                        removed.push(entry.name.clone());
+                        false
+                    }
+                    false => true,
+                });
+            }
+        }
+        check_removed("remove_deps", "dependency", names, &removed)
+    }
+
+    fn remove_scripts(&mut self, names: &[String]) -> Result<(), String> {
+        let mut removed = vec![];
+        if let Some(scripts) = &mut self.scripts {
+            for scripts in [&mut scripts.npm, &mut scripts.cargo].into_iter().flatten() {
+                for name in names {
+                    if scripts.shift_remove(name).is_some() {
+                        removed.push(name.clone());
+                    }
+                }
+            }
+        }
+        check_removed("remove_scripts", "script", names, &removed)
+    }
+}
+
+impl DepTables {
+    /// Merges `deps` over these per package manager. An entry with the same name and `when`
+    /// condition as an existing one replaces it, other entries are appended.
+    pub fn merge(mut self, deps: DepTables) -> DepTables {
+        for (entries, merged) in [(&mut self.npm, deps.npm), (&mut self.cargo, deps.cargo)] {
+            let Some(merged) = merged else {
+                continue;
+            };
+            let entries = entries.get_or_insert_with(Vec::new);
+            for entry in merged {
+                match entries
+                    .iter_mut()
+                    .find(|existing| existing.name == entry.name && existing.when == entry.when)
+                {
+                    Some(existing) => *existing = entry,
+                    None => entries.push(entry),
+                }
+            }
+        }
+        self
+    }
+}
+
+impl ScriptTables {
+    fn merge(mut self, scripts: ScriptTables) -> ScriptTables {
+        for (entries, merged) in [
+            (&mut self.npm, scripts.npm),
+            (&mut self.cargo, scripts.cargo),
+        ] {
+            if let Some(merged) = merged {
+                entries.get_or_insert_with(IndexMap::new).extend(merged);
+            }
+        }
+        self
+    }
+}
+
+impl Subfolders {
+    /// Paths of the leaf folders, creating them creates the whole tree
+    pub fn paths(&self) -> Vec<PathBuf> {
+        let mut paths = vec![];
+        self.collect_paths(Path::new(""), &mut paths);
+        paths
+    }
+
+    fn collect_paths(&self, path: &Path, paths: &mut Vec<PathBuf>) {
+        for (name, children) in &self.0 {
+            let path = path.join(name);
+            match children.0.is_empty() {
+                true => paths.push(path),
+                false => children.collect_paths(&path, paths),
+            }
+        }
+    }
+
+    fn merge(mut self, subfolders: Subfolders) -> Subfolders {
+        for (name, children) in subfolders.0 {
+            match self.0.get_mut(&name) {
+                Some(existing) => *existing = std::mem::take(existing).merge(children),
+                None => {
+                    self.0.insert(name, children);
+                }
+            }
+        }
+        self
+    }
+}
+
+impl PromptEntry {
+    fn merge(&mut self, prompt: PromptEntry) {
+        self.kind = prompt.kind.or(self.kind.take());
+        self.message = prompt.message.or(self.message.take());
+        self.help = prompt.help.or(self.help.take());
+        self.default = prompt.default.or(self.default.take());
+        self.options = prompt.options.or(self.options.take());
+        self.when = prompt.when.or(self.when.take());
+    }
+}
+
+impl Linters {
+    fn merge(self, linters: Linters) -> Linters {
+        match (self, linters) {
+            (Linters::Conditional(mut entries), Linters::Conditional(merged)) => {
+                for (linter, entry) in merged {
+                    let existing = entries.entry(linter).or_default();
+                    existing.when = entry.when.or(existing.when.take());
+                }
+                Linters::Conditional(entries)
+            }
+            (_, linters) => linters,
+        }
+    }
+}
+
+impl AddonEntry {
+    /// Replaces what `addon` sets, per package manager for its deps and per script for its
+    /// scripts
+    fn merge(&mut self, addon: AddonEntry) {
+        self.label = addon.label.or(self.label.take());
+        self.description = addon.description.or(self.description.take());
+        self.files = addon.files.or(self.files.take());
+        self.then = addon.then.or(self.then.take());
+        self.deps = merge_with(self.deps.take(), addon.deps, |deps, merged| DepTables {
+            npm: merged.npm.or(deps.npm),
+            cargo: merged.cargo.or(deps.cargo),
+        });
+        self.scripts = merge_with(self.scripts.take(), addon.scripts, ScriptTables::merge);
+    }
+}
+
+/// Merges `child` over `parent` when both are set, otherwise keeps whichever is
+fn merge_with<T>(parent: Option<T>, child: Option<T>, merge: impl FnOnce(T, T) -> T) -> Option<T> {
+    match (parent, child) {
+        (Some(parent), Some(child)) => Some(merge(parent, child)),
+        (parent, child) => child.or(parent),
+    }
+}
+
+/// Removing something the parent doesn't have is most likely a typo or upstream rename
+fn check_removed(
+    key: &str,
+    what: &str,
+    names: &[String],
+    removed: &[String],
+) -> Result<(), String> {
+    match names.iter().find(|name| !removed.contains(name)) {
+        Some(name) => Err(format!(
+            "`{}`: the extended stack has no {} `{}`",
+            key, what, name
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Named so the schema refers to the tree recursively instead of inlining it forever
+impl JsonSchema for Subfolders {
+    fn schema_name() -> String {
+        "Subfolders".to_string()
+    }
+
+    fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+        IndexMap::<String, Subfolders>::json_schema(gen)
+    }
+}
+
+/// A prompt's `default` may be any value
+fn any_value(_: &mut SchemaGenerator) -> Schema {
+    Schema::Bool(true)
+}
+
+impl<'de> Deserialize<'de> for Linters {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct LintersVisitor;
+
+        impl<'de> de::Visitor<'de> for LintersVisitor {
+            type Value = Linters;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("an array of linters or a table of linters")
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, seq: A) -> Result<Linters, A::Error> {
+                Vec::deserialize(SeqAccessDeserializer::new(seq)).map(Linters::Names)
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<Linters, A::Error> {
+                IndexMap::deserialize(MapAccessDeserializer::new(map)).map(Linters::Conditional)
+            }
+        }
+
+        deserializer.deserialize_any(LintersVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    #[test]
+    fn round_trip_manifests() {
+        let mut paths = vec![PathBuf::from("test/__mocks__/_test.toml")];
+        for entry in fs::read_dir("templates").expect("Error reading templates") {
+            let path = entry.expect("Error reading templates").path();
+            if path.join("stack_template.toml").is_file() {
+                paths.push(path.join("stack_template.toml"));
+            }
+        }
+
+        for path in paths {
+            let source = fs::read_to_string(&path).expect("Error reading manifest");
+            let manifest: Manifest = toml::from_str(&source).expect("Error parsing manifest");
+            let written = toml::to_string_pretty(&manifest).expect("Error writing manifest");
+            let reparsed: Manifest = toml::from_str(&written).expect("Error parsing written");
+            assert_eq!(manifest, reparsed, "{}", path.display());
+        }
+    }
+
+    #[test]
+    fn reject_unknown_fields() {
+        let error = toml::from_str::<Manifest>(
+            r#"
+            [[deps.cargo]]
+            name = "serde"
+            feature = ["derive"]
+            "#,
+        )
+        .unwrap_err();
+        assert!(error.message().starts_with("unknown field `feature`"));
+
+        let manifest: Manifest = toml::from_str(
+            r#"
+            [subfolders.src]
+            routes = {}
+            models = {}
+            "#,
+        )
+        .expect("Error parsing subfolders");
+        assert_eq!(
+            manifest.subfolders.unwrap().paths(),
+            [Path::new("src/routes"), Path::new("src/models")]
+        );
+    }
+
+    #[test]
+    fn merge_over_extended_manifest() {
+        let parse = |source: &str| toml::from_str::<Manifest>(source).unwrap();
+        let parent = parse(
+            r#"
+            title = "base"
+            web = true
+            groups = ["serde"]
+
+            [[deps.cargo]]
+            name = "axum"
+
+            [[deps.cargo]]
+            name = "tokio"
+            features = ["full"]
+
+            [prompts.auth]
+            type = "bool"
+            message = "Add auth?"
+
+            [subfolders.src]
+            routes = {}
+            "#,
+        );
+        let child = parse(
+            r#"
+            extends = "base"
+            remove_deps = ["axum"]
+            groups = ["strum"]
+
+            [[deps.cargo]]
+            name = "tokio"
+            features = ["rt"]
+
+            [prompts.auth]
+            default = true
+
+            [subfolders.src]
+            models = {}
+            "#,
+        );
+
+        let merged = Manifest::merge(parent.clone(), child).unwrap();
+        assert_eq!(merged.title, None);
+        assert_eq!(merged.web, Some(true));
+        assert_eq!(merged.groups, ["serde", "strum"]);
+        let deps = merged.deps.unwrap().cargo.unwrap();
+        assert_eq!(deps.len(), 1);
+        assert_eq!(deps[0].features, Some(vec!["rt".to_string()]));
+        assert_eq!(merged.prompts["auth"].message.as_deref(), Some("Add auth?"));
+        assert_eq!(merged.prompts["auth"].default, Some(Value::Boolean(true)));
+        assert_eq!(
+            merged.subfolders.unwrap().paths(),
+            [Path::new("src/routes"), Path::new("src/models")]
+        );
+
+        let error = Manifest::merge(parent, parse("remove_deps = ['hono']")).unwrap_err();
+        assert_eq!(
+            error,
+            "`remove_deps`: the extended stack has no dependency `hono`"
+        );
+    }
+}
@@ -1,92 +1,505 @@
 use std::process::Command;
 
-#[derive(Debug, Clone)]
+use indexmap::IndexMap;
+use schemars::JsonSchema;
+use serde::{de, Deserialize, Deserializer, Serialize};
+
+use crate::{config::PackageManager, expression::Expr, version};
+
+/// A `[[deps.<package manager>]]` entry of a manifest
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub struct Module {
     pub name: String,
+    #[serde(default = "latest", skip_serializing_if = "is_latest")]
     pub version: String,
+    #[serde(default, skip_serializing_if = "is_false")]
     pub dev: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub features: Option<Vec<String>>,
+    #[serde(
+        default,
+        deserialize_with = "deserialize_then",
+        skip_serializing_if = "Option::is_none"
+    )]
+    #[schemars(with = "Option<ThenCommands>")]
     pub then: Option<ThenCommands>,
+    /// Only installed when this holds for the user's answers
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(with = "Option<String>")]
+    pub when: Option<Expr>,
+    /// Git repository a cargo dep is fetched from, optionally pinned with `branch`, `tag` or `rev`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub git: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub branch: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tag: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rev: Option<String>,
+    /// Local folder of a cargo dep, relative to the project
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    #[serde(
+        rename = "default-features",
+        default = "enabled",
+        skip_serializing_if = "is_true"
+    )]
+    pub default_features: bool,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub optional: bool,
+    /// Name the crate `name` is imported as
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rename: Option<String>,
+    /// Crate installed when `name` is the name it is imported as, like Cargo.toml's `package`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub package: Option<String>,
+    /// Installs into `[build-dependencies]`
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub build: bool,
+    /// Platform a cargo dep is limited to, e.g. `cfg(windows)`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target: Option<String>,
+    /// Installs an npm dep into `peerDependencies`
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub peer: bool,
+    /// Records the exact npm version instead of a `^` range
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub exact: bool,
+    /// Package installed under `name`, e.g. `npm:string-width@^4`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alias: Option<String>,
+    /// URL of the `.tgz` an npm dep is installed from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tarball: Option<String>,
+    /// Local folder of an npm dep, relative to the project
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    /// Registry a scoped npm dep's scope is fetched from, e.g. `https://npm.pkg.github.com`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub registry: Option<String>,
 }
 
 pub type ThenCommands = Vec<Vec<String>>;
 
+fn latest() -> String {
+    version::LATEST.to_string()
+}
+
+fn is_latest(value: &str) -> bool {
+    value == version::LATEST
+}
+
+fn is_false(value: &bool) -> bool {
+    !value
+}
+
+fn enabled() -> bool {
+    true
+}
+
+fn is_true(value: &bool) -> bool {
+    *value
+}
+
 impl Module {
-    pub fn new(
-        name: String,
-        version: String,
-        dev: bool,
-        then: Option<ThenCommands>,
-        features: Option<Vec<String>>,
-    ) -> Self {
-        Self {
-            name,
-            version,
-            dev,
-            features,
-            then,
+    /// Checks what the manifest's types can't: the version syntax of the package manager and
+    /// the keys that can't be combined
+    pub fn check(&self, manager: &PackageManager) -> Result<(), String> {
+        version::normalize(manager, &self.version)?;
+        let conflict = self
+            .foreign_key(manager)
+            .or_else(|| self.git_ref_conflict())
+            .or_else(|| match manager {
+                PackageManager::Cargo => self.cargo_conflict(),
+                PackageManager::Npm => self.npm_conflict(),
+            });
+        match conflict {
+            Some(conflict) => Err(conflict),
+            None => Ok(()),
         }
     }
-}
 
-pub fn get_npm_cmds(npm_modules: &Vec<Module>) -> Vec<Command> {
-    let mut commands = vec![];
-    for module in npm_modules {
-        let mut command = Command::new("bun");
-        command.arg("add");
+    /// The first key set that only the other package manager uses. Both share one `Module`
+    /// type, so the schema accepts every key for either.
+    fn foreign_key(&self, manager: &PackageManager) -> Option<String> {
+        let (keys, owner) = match manager {
+            PackageManager::Npm => (
+                vec![
+                    ("features", self.features.is_some()),
+                    ("path", self.path.is_some()),
+                    ("default-features", !self.default_features),
+                    ("rename", self.rename.is_some()),
+                    ("package", self.package.is_some()),
+                    ("build", self.build),
+                    ("target", self.target.is_some()),
+                ],
+                "cargo",
+            ),
+            PackageManager::Cargo => (
+                vec![
+                    ("peer", self.peer),
+                    ("exact", self.exact),
+                    ("alias", self.alias.is_some()),
+                    ("tarball", self.tarball.is_some()),
+                    ("file", self.file.is_some()),
+                    ("registry", self.registry.is_some()),
+                ],
+                "npm",
+            ),
+        };
+        keys.into_iter()
+            .find(|(_, set)| *set)
+            .map(|(key, _)| format!("`{}` only applies to {} deps", key, owner))
+    }
+
+    fn git_refs(&self) -> Vec<(&'static str, &String)> {
+        [
+            ("branch", &self.branch),
+            ("tag", &self.tag),
+            ("rev", &self.rev),
+        ]
+        .into_iter()
+        .filter_map(|(key, value)| Some((key, value.as_ref()?)))
+        .collect()
+    }
 
-        if module.version != "latest" {
-            command.arg(format!("{}@{}", module.name, module.version));
+    fn git_ref_conflict(&self) -> Option<String> {
+        let git_refs = self.git_refs();
+        if self.git.is_none() && !git_refs.is_empty() {
+            Some(format!("`{}` requires `git`", git_refs[0].0))
+        } else if git_refs.len() > 1 {
+            Some(format!(
+                "`{}` and `{}` can't be combined, pin one git reference",
+                git_refs[0].0, git_refs[1].0
+            ))
+        } else if self.git.is_some() && self.version != version::LATEST {
+            Some(
+                "`version` can't be combined with `git`, pin `branch`, `tag` or `rev` instead"
+                    .to_string(),
+            )
         } else {
-            command.arg(&module.name);
+            None
         }
+    }
 
-        if module.dev {
-            command.arg("--dev");
+    fn cargo_conflict(&self) -> Option<String> {
+        if self.git.is_some() && self.path.is_some() {
+            Some("`git` and `path` are two different sources, use one".to_string())
+        } else if self.rename.is_some() && self.package.is_some() {
+            Some("`rename` and `package` both name the imported crate, use one".to_string())
+        } else if self.dev && self.build {
+            Some("`dev` and `build` install into different tables, use one".to_string())
+        } else {
+            None
         }
+    }
 
-        commands.push(command);
+    fn npm_conflict(&self) -> Option<String> {
+        let sources = [
+            ("alias", &self.alias),
+            ("git", &self.git),
+            ("tarball", &self.tarball),
+            ("file", &self.file),
+        ]
+        .into_iter()
+        .filter(|(_, value)| value.is_some())
+        .map(|(key, _)| key)
+        .collect::<Vec<_>>();
+        let tables = [
+            ("dev", self.dev),
+            ("peer", self.peer),
+            ("optional", self.optional),
+        ]
+        .into_iter()
+        .filter(|(_, value)| *value)
+        .map(|(key, _)| key)
+        .collect::<Vec<_>>();
 
-        if let Some(then_commands) = &module.then {
-            commands.append(&mut generate_then_cmds(&then_commands));
+        if sources.len() > 1 {
+            Some(format!(
+                "`{}` and `{}` are two different sources, use one",
+                sources[0], sources[1]
+            ))
+        } else if !sources.is_empty() && self.version != version::LATEST {
+            Some(format!(
+                "`version` can't be combined with `{}`, which names what to install",
+                sources[0]
+            ))
+        } else if tables.len() > 1 {
+            Some(format!(
+                "`{}` and `{}` install into different package.json tables, use one",
+                tables[0], tables[1]
+            ))
+        } else if self.exact && self.npm_source().is_some() {
+            Some("`exact` only applies to versions from the registry".to_string())
+        } else if self.registry.is_some() && self.npm_source().is_some() {
+            Some("`registry` only applies to versions from the registry".to_string())
+        } else if self.registry.is_some() && self.npm_scope().is_none() {
+            Some(format!(
+                "`registry` requires a scoped package such as `@acme/{}`",
+                self.name
+            ))
+        } else if let Some(alias) = &self.alias {
+            Self::alias_conflict(alias)
+        } else {
+            None
         }
     }
 
-    commands
-}
+    /// Aliases name the installed package and its range, e.g. `npm:@acme/ui@^2`
+    fn alias_conflict(alias: &str) -> Option<String> {
+        let malformed = || {
+            Some(format!(
+                "`alias` must look like `npm:<package>@<range>`, found `{}`",
+                alias
+            ))
+        };
+        let Some(package) = alias.strip_prefix("npm:") else {
+            return malformed();
+        };
+        // the `@` of a scope is part of the package name
+        let (scope, unscoped) = match package.strip_prefix('@') {
+            Some(scoped) => match scoped.split_once('/') {
+                Some((scope, unscoped)) if !scope.is_empty() => (Some(scope), unscoped),
+                _ => return malformed(),
+            },
+            None => (None, package),
+        };
+        let (name, range) = unscoped
+            .split_once('@')
+            .unwrap_or((unscoped, version::LATEST));
+        if name.is_empty() || range.is_empty() || scope.is_some_and(str::is_empty) {
+            return malformed();
+        }
+        version::normalize(&PackageManager::Npm, range)
+            .err()
+            .map(|err| format!("`alias`: {}", err))
+    }
 
-pub fn get_cargo_cmds(cargo_modules: &Vec<Module>) -> Vec<Command> {
-    let mut commands = vec![];
-    for module in cargo_modules {
-        let mut command = Command::new("cargo");
-        command.env("CARGO_NET_GIT_FETCH_WITH_CLI", "true");
-        command.arg("add");
+    /// The scope of a scoped npm package, e.g. `@acme` for `@acme/ui`
+    fn npm_scope(&self) -> Option<&str> {
+        let (scope, _) = self.name.split_once('/')?;
+        scope.starts_with('@').then_some(scope)
+    }
 
-        if module.version != "latest" {
-            command.arg(format!("{}@{}", module.name, module.version));
+    /// Where an npm dep that doesn't come from the registry is installed from, as package.json
+    /// records it
+    fn npm_source(&self) -> Option<String> {
+        if let Some(git) = &self.git {
+            match self.git_refs().first() {
+                Some((_, reference)) => Some(format!("{}#{}", git, reference)),
+                None => Some(git.clone()),
+            }
+        } else if let Some(file) = &self.file {
+            Some(format!("file:{}", file))
         } else {
-            command.arg(&module.name);
+            self.tarball.clone()
         }
+    }
 
-        if module.dev {
-            command.arg("--dev");
+    /// The package.json table an npm dep is recorded in
+    fn npm_table(&self) -> &'static str {
+        if self.dev {
+            "devDependencies"
+        } else if self.peer {
+            "peerDependencies"
+        } else if self.optional {
+            "optionalDependencies"
+        } else {
+            "dependencies"
         }
+    }
+}
 
-        if let Some(features) = &module.features {
-            command.arg("--features");
-            command.arg(features.join(","));
+/// Reads `then` commands, each an array holding the program and its arguments
+pub fn deserialize_then<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<ThenCommands>, D::Error> {
+    let commands = ThenCommands::deserialize(deserializer)?;
+    if commands.iter().any(Vec::is_empty) {
+        return Err(de::Error::custom("`then` contains an empty command"));
+    }
+    Ok(Some(commands))
+}
+
+/// Installs npm modules with as few `bun add` runs as possible: one per combination of flags,
+/// restarted after each module with `then` commands so they run once it is installed
+pub fn get_npm_cmds(npm_modules: &[Module]) -> Vec<Command> {
+    let mut commands = vec![];
+    for modules in then_segments(npm_modules) {
+        let mut sources = vec![];
+        let mut batches: IndexMap<Vec<&str>, Vec<String>> = IndexMap::new();
+        for module in modules {
+            if let (Some(registry), Some(scope)) = (&module.registry, module.npm_scope()) {
+                // recorded in the project's .npmrc, which bun reads too
+                let mut command = Command::new("npm");
+                command
+                    .args(["config", "set", "--location=project"])
+                    .arg(format!("{}:registry={}", scope, registry));
+                commands.push(command);
+            }
+
+            // bun add can't install git, tarball and file deps under a chosen name, so they are
+            // written to package.json and installed from there
+            if let Some(source) = module.npm_source() {
+                sources.push(format!("{}.{}={}", module.npm_table(), module.name, source));
+                continue;
+            }
+            let spec = if let Some(alias) = &module.alias {
+                format!("{}@{}", module.name, alias)
+            } else if module.version != version::LATEST {
+                format!("{}@{}", module.name, module.version)
+            } else {
+                module.name.clone()
+            };
+            let flags = [
+                ("--dev", module.dev),
+                ("--peer", module.peer),
+                ("--optional", module.optional),
+                ("--exact", module.exact),
+            ]
+            .into_iter()
+            .filter(|(_, enabled)| *enabled)
+            .map(|(flag, _)| flag)
+            .collect();
+            batches.entry(flags).or_default().push(spec);
         }
 
-        commands.push(command);
+        if !sources.is_empty() {
+            let mut command = Command::new("npm");
+            command.args(["pkg", "set"]).args(&sources);
+            commands.push(command);
+            let mut command = Command::new("bun");
+            command.arg("install");
+            commands.push(command);
+        }
+        for (flags, specs) in batches {
+            let mut command = Command::new("bun");
+            command.arg("add").args(specs).args(flags);
+            commands.push(command);
+        }
+        commands.append(&mut segment_then_cmds(modules));
+    }
+
+    commands
+}
 
-        if let Some(then_commands) = &module.then {
-            commands.append(&mut generate_then_cmds(then_commands))
+/// Installs cargo modules with as few `cargo add` runs as possible: one per combination of
+/// flags, restarted after each module with `then` commands so they run once it is installed.
+/// Crates from git or a path and renamed crates take flags `cargo add` only allows for a single
+/// crate, so they are added on their own.
+pub fn get_cargo_cmds(cargo_modules: &[Module]) -> Vec<Command> {
+    let mut commands = vec![];
+    for modules in then_segments(cargo_modules) {
+        let mut batches: IndexMap<Vec<String>, Vec<&Module>> = IndexMap::new();
+        for module in modules {
+            let single_crate = module.git.is_some()
+                || module.path.is_some()
+                || module.rename.is_some()
+                || module.package.is_some();
+            if single_crate {
+                commands.push(cargo_add(&[module], &cargo_shared_flags(module)));
+            } else {
+                batches
+                    .entry(cargo_shared_flags(module))
+                    .or_default()
+                    .push(module);
+            }
+        }
+
+        for (flags, modules) in batches {
+            commands.push(cargo_add(&modules, &flags));
         }
+        commands.append(&mut segment_then_cmds(modules));
     }
     commands
 }
 
-fn generate_then_cmds(then_commands: &ThenCommands) -> Vec<Command> {
+/// Flags that apply to every crate of a `cargo add` run
+fn cargo_shared_flags(module: &Module) -> Vec<String> {
+    let mut flags = vec![];
+    if let Some(target) = &module.target {
+        flags.extend(["--target".to_string(), target.clone()]);
+    }
+    let switches = [
+        ("--dev", module.dev),
+        ("--build", module.build),
+        ("--optional", module.optional),
+        ("--no-default-features", !module.default_features),
+    ];
+    for (flag, enabled) in switches {
+        if enabled {
+            flags.push(flag.to_string());
+        }
+    }
+    flags
+}
+
+fn cargo_add(modules: &[&Module], shared_flags: &[String]) -> Command {
+    let mut command = Command::new("cargo");
+    command.env("CARGO_NET_GIT_FETCH_WITH_CLI", "true");
+    command.arg("add");
+
+    let mut features = vec![];
+    for module in modules {
+        // with `package`, `name` is the name the crate is imported as
+        let crate_name = module.package.as_ref().unwrap_or(&module.name);
+        if module.version != version::LATEST {
+            command.arg(format!("{}@{}", crate_name, module.version));
+        } else {
+            command.arg(crate_name);
+        }
+        for feature in module.features.iter().flatten() {
+            // features of several crates are told apart by the crate's name
+            match modules.len() {
+                1 => features.push(feature.clone()),
+                _ => features.push(format!("{}/{}", crate_name, feature)),
+            }
+        }
+    }
+
+    // single crate flags, only set on modules that are added on their own
+    let module = modules[0];
+    let options = [
+        ("--git", &module.git),
+        ("--branch", &module.branch),
+        ("--tag", &module.tag),
+        ("--rev", &module.rev),
+        ("--path", &module.path),
+    ];
+    for (flag, value) in options {
+        if let Some(value) = value {
+            command.args([flag, value]);
+        }
+    }
+    match (&module.package, &module.rename) {
+        (Some(_), _) => command.args(["--rename", &module.name]),
+        (None, Some(rename)) => command.args(["--rename", rename]),
+        (None, None) => &mut command,
+    };
+
+    command.args(shared_flags);
+    if !features.is_empty() {
+        command.arg("--features");
+        command.arg(features.join(","));
+    }
+    command
+}
+
+/// Runs of modules ending with one that has `then` commands, or with the last module
+fn then_segments(modules: &[Module]) -> impl Iterator<Item = &[Module]> {
+    modules.split_inclusive(|module| module.then.is_some())
+}
+
+fn segment_then_cmds(modules: &[Module]) -> Vec<Command> {
+    match modules.last().and_then(|module| module.then.as_ref()) {
+        Some(then_commands) => generate_then_cmds(then_commands),
+        None => vec![],
+    }
+}
+
+pub fn generate_then_cmds(then_commands: &ThenCommands) -> Vec<Command> {
     let mut commands = vec![];
     for cmd in then_commands {
         let mut command = Command::new(&cmd[0]);
@@ -97,3 +510,268 @@ fn generate_then_cmds(then_commands: &ThenCommands) -> Vec<Command> {
     }
     commands
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::manifest::DepTables;
+
+    fn cargo_args(dep: &str) -> Vec<String> {
+        let module: Module = toml::from_str(dep).expect("Error parsing dep");
+        module.check(&PackageManager::Cargo).expect("Invalid dep");
+        let commands = get_cargo_cmds(&[module]);
+        commands[0]
+            .get_args()
+            .map(|arg| arg.to_string_lossy().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn map_cargo_specs_to_flags() {
+        assert_eq!(
+            cargo_args(
+                r#"
+                name = "internal"
+                git = "https://example.com/internal.git"
+                branch = "main"
+                default-features = false
+                optional = true
+                features = ["a", "b"]
+                "#
+            ),
+            [
+                "add",
+                "internal",
+                "--git",
+                "https://example.com/internal.git",
+                "--branch",
+                "main",
+                "--optional",
+                "--no-default-features",
+                "--features",
+                "a,b"
+            ]
+        );
+        assert_eq!(
+            cargo_args(
+                r#"
+                name = "shared"
+                package = "shared-types"
+                path = "../shared"
+                version = "0.3"
+                build = true
+                target = "cfg(unix)"
+                "#
+            ),
+            [
+                "add",
+                "shared-types@0.3",
+                "--path",
+                "../shared",
+                "--rename",
+                "shared",
+                "--target",
+                "cfg(unix)",
+                "--build"
+            ]
+        );
+    }
+
+    #[test]
+    fn reject_conflicting_cargo_specs() {
+        let check = |dep: &str| {
+            let module: Module = toml::from_str(dep).expect("Error parsing dep");
+            module.check(&PackageManager::Cargo).unwrap_err()
+        };
+        assert_eq!(
+            check("name = 'a'\nbranch = 'main'"),
+            "`branch` requires `git`"
+        );
+        assert!(
+            check("name = 'a'\ngit = 'url'\ntag = 'v1'\nrev = 'abc'").contains("can't be combined")
+        );
+        assert!(check("name = 'a'\ngit = 'url'\npath = '../a'").contains("two different sources"));
+        assert!(check("name = 'a'\ngit = 'url'\nversion = '1'").contains("`version`"));
+        assert!(check("name = 'a'\nrename = 'b'\npackage = 'c'").contains("use one"));
+        assert!(check("name = 'a'\ndev = true\nbuild = true").contains("use one"));
+        assert_eq!(
+            check("name = 'a'\nfile = '../a'"),
+            "`file` only applies to npm deps"
+        );
+    }
+
+    fn npm_commands(dep: &str) -> Vec<Vec<String>> {
+        let module: Module = toml::from_str(dep).expect("Error parsing dep");
+        module.check(&PackageManager::Npm).expect("Invalid dep");
+        get_npm_cmds(&[module])
+            .iter()
+            .map(|command| {
+                std::iter::once(command.get_program())
+                    .chain(command.get_args())
+                    .map(|arg| arg.to_string_lossy().to_string())
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn map_npm_specs_to_commands() {
+        assert_eq!(
+            npm_commands("name = 'react'\nversion = '^18'\npeer = true\nexact = true"),
+            [["bun", "add", "react@^18", "--peer", "--exact"]]
+        );
+        assert_eq!(
+            npm_commands("name = 'string-width-cjs'\nalias = 'npm:string-width@^4.2.0'"),
+            [["bun", "add", "string-width-cjs@npm:string-width@^4.2.0"]]
+        );
+        assert_eq!(
+            npm_commands(
+                "name = '@acme/ui'\nregistry = 'https://npm.pkg.github.com'\noptional = true"
+            ),
+            [
+                vec![
+                    "npm",
+                    "config",
+                    "set",
+                    "--location=project",
+                    "@acme:registry=https://npm.pkg.github.com"
+                ],
+                vec!["bun", "add", "@acme/ui", "--optional"]
+            ]
+        );
+        assert_eq!(
+            npm_commands(
+                "name = 'ui'\ngit = 'github:acme/ui'\ntag = 'v2.0.0'\ndev = true\nthen = [['bun', 'run', 'build']]"
+            ),
+            [
+                vec!["npm", "pkg", "set", "devDependencies.ui=github:acme/ui#v2.0.0"],
+                vec!["bun", "install"],
+                vec!["bun", "run", "build"]
+            ]
+        );
+        assert_eq!(
+            npm_commands("name = 'shared'\nfile = '../shared'"),
+            [
+                vec!["npm", "pkg", "set", "dependencies.shared=file:../shared"],
+                vec!["bun", "install"]
+            ]
+        );
+    }
+
+    #[test]
+    fn reject_conflicting_npm_specs() {
+        let check = |dep: &str| {
+            let module: Module = toml::from_str(dep).expect("Error parsing dep");
+            module.check(&PackageManager::Npm).unwrap_err()
+        };
+        assert!(check("name = 'a'\ngit = 'url'\nfile = '../a'").contains("two different sources"));
+        assert!(check("name = 'a'\ntarball = 'url'\nversion = '1'").contains("`version`"));
+        assert!(check("name = 'a'\ndev = true\npeer = true").contains("package.json tables"));
+        assert!(check("name = 'a'\nfile = '../a'\nexact = true").contains("`exact`"));
+        assert_eq!(
+            check("name = 'a'\nregistry = 'url'"),
+            "`registry` requires a scoped package such as `@acme/a`"
+        );
+        assert!(check("name = 'a'\nalias = 'b@1'").contains("npm:<package>@<range>"));
+        assert!(check("name = 'a'\nalias = 'npm:@b/c@~> 1'").contains("RubyGems"));
+        for alias in ["npm:", "npm:@", "npm:@/c", "npm:@b/", "npm:c@"] {
+            let dep = format!("name = 'a'\nalias = '{}'", alias);
+            assert!(check(&dep).contains("must look like"), "{}", alias);
+        }
+        assert!(check("name = 'a'\nalias = 'npm:é@~> 1'").contains("RubyGems"));
+        assert_eq!(check("name = 'a'\ntag = 'v1'"), "`tag` requires `git`");
+        assert_eq!(
+            check("name = 'a'\nfeatures = ['derive']"),
+            "`features` only applies to cargo deps"
+        );
+    }
+
+    fn command_lines(commands: Vec<Command>) -> Vec<String> {
+        commands
+            .iter()
+            .map(|command| {
+                std::iter::once(command.get_program())
+                    .chain(command.get_args())
+                    .map(|arg| arg.to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            })
+            .collect()
+    }
+
+    #[test]
+    fn batch_installs_around_then_commands() {
+        let deps: DepTables = toml::from_str(
+            r#"
+            [[cargo]]
+            name = "serde"
+            features = ["derive"]
+
+            [[cargo]]
+            name = "tokio"
+            version = "1"
+            features = ["full"]
+
+            [[cargo]]
+            name = "insta"
+            dev = true
+
+            [[cargo]]
+            name = "shared"
+            path = "../shared"
+
+            [[cargo]]
+            name = "sqlx-cli"
+            then = [["sqlx", "database", "create"]]
+
+            [[cargo]]
+            name = "axum"
+
+            [[cargo]]
+            name = "pretty_assertions"
+            dev = true
+
+            [[npm]]
+            name = "hono"
+
+            [[npm]]
+            name = "ui"
+            file = "../ui"
+
+            [[npm]]
+            name = "typescript"
+            dev = true
+
+            [[npm]]
+            name = "zod"
+            version = "^3"
+
+            [[npm]]
+            name = "vitest"
+            dev = true
+            "#,
+        )
+        .expect("Error parsing deps");
+
+        assert_eq!(
+            command_lines(get_cargo_cmds(&deps.cargo.unwrap())),
+            [
+                "cargo add shared --path ../shared",
+                "cargo add serde tokio@1 sqlx-cli --features serde/derive,tokio/full",
+                "cargo add insta --dev",
+                "sqlx database create",
+                "cargo add axum",
+                "cargo add pretty_assertions --dev",
+            ]
+        );
+        assert_eq!(
+            command_lines(get_npm_cmds(&deps.npm.unwrap())),
+            [
+                "npm pkg set dependencies.ui=file:../ui",
+                "bun install",
+                "bun add hono zod@^3",
+                "bun add typescript vitest --dev",
+            ]
+        );
+    }
+}
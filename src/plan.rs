@@ -0,0 +1,170 @@
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use crate::{
+    answers::ANSWERS_FILE,
+    builder::{self, ExistingDir},
+    config::ScaffoldConfig,
+    container,
+    error::Result,
+    file_system, module,
+};
+
+/// Prints every step `ProjectBuilder::build` would take for this config, in order, without
+/// creating any files or running any commands.
+pub fn print_plan(
+    config: &ScaffoldConfig,
+    existing: ExistingDir,
+    save_answers: Option<&Path>,
+) -> Result<()> {
+    let steps = plan_steps(config, existing, save_answers)?;
+    println!(
+        "Scaffold plan for {} from {}",
+        config.title,
+        config.template_dir.display()
+    );
+    println!("Commands run inside {}\n", config.root_dir.display());
+    let width = steps.len().to_string().len();
+    for (index, step) in steps.iter().enumerate() {
+        println!("{:>width$}. {}", index + 1, step, width = width);
+    }
+    println!("\nDry run: nothing was written and no commands were run.");
+    Ok(())
+}
+
+fn plan_steps(
+    config: &ScaffoldConfig,
+    existing: ExistingDir,
+    save_answers: Option<&Path>,
+) -> Result<Vec<String>> {
+    let root = &config.root_dir;
+    let kept = builder::check_existing(config, existing)?;
+    let mut steps: Vec<String> = vec![];
+
+    // `--save-answers` writes its file before the build starts
+    if let Some(path) = save_answers {
+        steps.push(format!("Write answers to {}", path.display()));
+    }
+
+    for folder in builder::get_folders(root, &config.subfolders) {
+        steps.push(format!("Create folder {}", folder.display()));
+    }
+
+    steps.append(&mut copy_steps(
+        &config.template_dirs,
+        "before_install",
+        root,
+        &kept,
+    ));
+
+    for command in builder::get_install_commands(config)? {
+        steps.push(run_step(&command));
+    }
+
+    for command in builder::get_template_engine_commands(config) {
+        steps.push(run_step(&command));
+    }
+    if let Some(command) = builder::get_spa_command(config) {
+        steps.push(format!("{} (interactive)", run_step(&command)));
+    }
+
+    for file in builder::get_boilerplate_files(config) {
+        steps.push(keep_or(&kept, root, &file, "Remove"));
+    }
+
+    steps.append(&mut copy_steps(
+        &config.template_dirs,
+        "after_install",
+        root,
+        &kept,
+    ));
+
+    for addon in &config.addons {
+        steps.append(&mut copy_steps(
+            &config.template_dirs,
+            &addon.files,
+            root,
+            &kept,
+        ));
+        for command in module::generate_then_cmds(&addon.then) {
+            steps.push(run_step(&command));
+        }
+    }
+
+    if config.containers {
+        for file in container::get_docker_files(&config.template_dirs) {
+            steps.push(keep_or(&kept, root, &file, "Render"));
+        }
+    }
+
+    if let Some(npm_scripts) = &config.npm_scripts {
+        for command in builder::get_npm_script_commands(npm_scripts) {
+            steps.push(run_step(&command));
+        }
+    }
+
+    steps.push(keep_or(&kept, root, Path::new(ANSWERS_FILE), "Write"));
+
+    for command in builder::get_repo_commands() {
+        steps.push(run_step(&command));
+    }
+    Ok(steps)
+}
+
+fn copy_steps(
+    template_dirs: &[PathBuf],
+    subfolder: impl AsRef<Path>,
+    root: &Path,
+    kept: &[PathBuf],
+) -> Vec<String> {
+    file_system::list_layered_files(template_dirs, subfolder)
+        .iter()
+        .map(|(file, src)| {
+            if kept.contains(file) {
+                keep_step(root, file)
+            } else {
+                format!(
+                    "Copy {} -> {}",
+                    src.join(file).display(),
+                    root.join(file).display()
+                )
+            }
+        })
+        .collect()
+}
+
+fn keep_or(kept: &[PathBuf], root: &Path, file: &Path, action: &str) -> String {
+    if kept.iter().any(|kept| kept == file) {
+        keep_step(root, file)
+    } else {
+        format!("{} {}", action, root.join(file).display())
+    }
+}
+
+fn keep_step(root: &Path, file: &Path) -> String {
+    format!("Keep existing {}", root.join(file).display())
+}
+
+fn run_step(command: &Command) -> String {
+    format!("Run: {}", builder::format_command(command))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dry_run_plans_the_answers_file_without_writing_it() {
+        let scratch = tempfile::tempdir().expect("Error creating scratch dir");
+        let config = ScaffoldConfig::for_stack("rscli", &scratch.path().join("billing"));
+        let answers = scratch.path().join("answers.toml");
+
+        let steps =
+            plan_steps(&config, ExistingDir::Abort, Some(&answers)).expect("Error planning");
+        assert_eq!(steps[0], format!("Write answers to {}", answers.display()));
+        assert!(!answers.exists());
+        assert!(!config.root_dir.exists());
+    }
+}
@@ -0,0 +1,427 @@
+use dialoguer::{theme::ColorfulTheme, Confirm, Input, MultiSelect, Select};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use toml::Value;
+
+use crate::{
+    error::{Context, Error, Result},
+    expression::{Expr, Variables},
+    manifest::PromptEntry,
+};
+
+/// Options every stack asks about, usable in `when` conditions but not as prompt names
+pub const BUILTIN_VARIABLES: [&str; 8] = [
+    "stack",
+    "name",
+    "db",
+    "orm",
+    "spa",
+    "template_engine",
+    "containers",
+    "addons",
+];
+
+/// Variables the container templates already receive, which a prompt can't shadow
+const TEMPLATE_VARIABLES: [&str; 6] = [
+    "app_name",
+    "deps_name",
+    "database",
+    "postgres",
+    "mongo",
+    "sqlite",
+];
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum PromptKind {
+    Bool,
+    Select,
+    MultiSelect,
+    String,
+    Int,
+}
+
+impl PromptKind {
+    /// Name of the kind as written in the manifest's `type` key
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            PromptKind::Bool => "bool",
+            PromptKind::Select => "select",
+            PromptKind::MultiSelect => "multiselect",
+            PromptKind::String => "string",
+            PromptKind::Int => "int",
+        }
+    }
+}
+
+/// A question declared under `[prompts.<name>]` in a stack_template.toml
+#[derive(Debug, Clone)]
+pub struct Prompt {
+    pub name: String,
+    pub kind: PromptKind,
+    pub message: String,
+    pub help: Option<String>,
+    pub default: Option<Value>,
+    /// Choices for select and multiselect prompts
+    pub options: Vec<String>,
+    pub when: Option<Expr>,
+}
+
+impl Prompt {
+    /// Parses a prompt table, `earlier` are the prompts declared before it that `when` may use
+    pub fn parse(
+        name: &str,
+        entry: &PromptEntry,
+        earlier: &[Prompt],
+    ) -> std::result::Result<Self, String> {
+        let context = format!("prompts.{}", name);
+        if !is_identifier(name) {
+            return Err(format!(
+                "`{}`: prompt names may only contain letters, digits and underscores",
+                context
+            ));
+        }
+        if BUILTIN_VARIABLES.contains(&name) || TEMPLATE_VARIABLES.contains(&name) {
+            return Err(format!(
+                "`{}`: `{}` is a built-in variable, pick another name",
+                context, name
+            ));
+        }
+
+        let kind = entry
+            .kind
+            .clone()
+            .ok_or_else(|| format!("`{}` is missing a type", context))?;
+        let options = entry.options.clone().unwrap_or_default();
+        match kind {
+            PromptKind::Select | PromptKind::MultiSelect if options.is_empty() => {
+                return Err(format!("`{}` needs at least one option", context))
+            }
+            PromptKind::Select | PromptKind::MultiSelect => {}
+            _ if !options.is_empty() => {
+                return Err(format!(
+                    "`{}.options` is only used by select and multiselect prompts",
+                    context
+                ))
+            }
+            _ => {}
+        }
+
+        let when = entry
+            .when
+            .as_deref()
+            .map(|source| parse_when(source, &context, earlier))
+            .transpose()?;
+
+        let mut prompt = Self {
+            name: name.to_string(),
+            kind,
+            message: entry.message.clone().unwrap_or_else(|| name.to_string()),
+            help: entry.help.clone(),
+            default: None,
+            options,
+            when,
+        };
+        if let Some(default) = &entry.default {
+            prompt
+                .check(default)
+                .map_err(|err| format!("`{}.default` is invalid: {}", context, err))?;
+            prompt.default = Some(default.clone());
+        }
+        Ok(prompt)
+    }
+
+    /// Checks an answer from an answers file or the template default fits the prompt
+    pub fn check(&self, value: &Value) -> std::result::Result<(), String> {
+        let check_option = |option: &str| {
+            if self.options.iter().any(|known| known == option) {
+                Ok(())
+            } else {
+                Err(format!(
+                    "`{}` is not one of {}",
+                    option,
+                    self.options.join(", ")
+                ))
+            }
+        };
+        match (&self.kind, value) {
+            (PromptKind::Bool, Value::Boolean(_))
+            | (PromptKind::String, Value::String(_))
+            | (PromptKind::Int, Value::Integer(_)) => Ok(()),
+            (PromptKind::Select, Value::String(option)) => check_option(option),
+            (PromptKind::MultiSelect, Value::Array(options)) => {
+                options.iter().try_for_each(|option| match option {
+                    Value::String(option) => check_option(option),
+                    _ => Err("expected an array of strings".to_string()),
+                })
+            }
+            (PromptKind::MultiSelect, _) => Err("expected an array of strings".to_string()),
+            (PromptKind::Bool, _) => Err("expected true or false".to_string()),
+            (PromptKind::Int, _) => Err("expected an integer".to_string()),
+            (PromptKind::Select | PromptKind::String, _) => Err("expected a string".to_string()),
+        }
+    }
+
+    /// Parses a `--set` value for this prompt, multiselect options are separated by commas
+    pub fn parse_value(&self, text: &str) -> std::result::Result<Value, String> {
+        let value = match self.kind {
+            PromptKind::Bool => match text {
+                "true" | "yes" | "y" => Value::Boolean(true),
+                "false" | "no" | "n" => Value::Boolean(false),
+                _ => return Err("expected true or false".to_string()),
+            },
+            PromptKind::Int => Value::Integer(
+                text.parse()
+                    .map_err(|_| "expected an integer".to_string())?,
+            ),
+            PromptKind::MultiSelect => Value::Array(
+                text.split(',')
+                    .map(str::trim)
+                    .filter(|option| !option.is_empty())
+                    .map(|option| Value::String(option.to_string()))
+                    .collect(),
+            ),
+            PromptKind::Select | PromptKind::String => Value::String(text.to_string()),
+        };
+        self.check(&value)?;
+        Ok(value)
+    }
+
+    /// The answer used when prompts are disabled and none was supplied
+    pub fn default_value(&self) -> Value {
+        if let Some(default) = &self.default {
+            return default.clone();
+        }
+        match self.kind {
+            PromptKind::Bool => Value::Boolean(false),
+            PromptKind::Select => Value::String(self.options[0].clone()),
+            PromptKind::MultiSelect => Value::Array(vec![]),
+            PromptKind::String => Value::String(String::new()),
+            PromptKind::Int => Value::Integer(0),
+        }
+    }
+
+    fn ask(&self) -> Result<Value> {
+        if let Some(help) = &self.help {
+            println!("{}", console::style(help).dim());
+        }
+        let theme = ColorfulTheme::default();
+        let default = self.default_value();
+        let value = match self.kind {
+            PromptKind::Bool => Confirm::with_theme(&theme)
+                .with_prompt(&self.message)
+                .default(default.as_bool().unwrap_or_default())
+                .interact()
+                .map(Value::Boolean),
+            PromptKind::Select => Select::with_theme(&theme)
+                .with_prompt(&self.message)
+                .items(&self.options)
+                .default(self.option_index(&default).unwrap_or_default())
+                .interact()
+                .map(|index| Value::String(self.options[index].clone())),
+            PromptKind::MultiSelect => {
+                let selected = self
+                    .options
+                    .iter()
+                    .map(|option| {
+                        default.as_array().is_some_and(|defaults| {
+                            defaults.iter().any(|d| d.as_str() == Some(option))
+                        })
+                    })
+                    .collect::<Vec<_>>();
+                MultiSelect::with_theme(&theme)
+                    .with_prompt(&self.message)
+                    .items(&self.options)
+                    .defaults(&selected)
+                    .interact()
+                    .map(|indexes| {
+                        Value::Array(
+                            indexes
+                                .into_iter()
+                                .map(|index| Value::String(self.options[index].clone()))
+                                .collect(),
+                        )
+                    })
+            }
+            PromptKind::String => Input::<String>::with_theme(&theme)
+                .with_prompt(&self.message)
+                .default(default.as_str().unwrap_or_default().to_string())
+                .allow_empty(true)
+                .interact_text()
+                .map(Value::String),
+            PromptKind::Int => Input::<i64>::with_theme(&theme)
+                .with_prompt(&self.message)
+                .default(default.as_integer().unwrap_or_default())
+                .interact_text()
+                .map(Value::Integer),
+        };
+        value.with_context(|| format!("Failed to get answer to \"{}\" from user", self.message))
+    }
+
+    fn option_index(&self, value: &Value) -> Option<usize> {
+        let value = value.as_str()?;
+        self.options.iter().position(|option| option == value)
+    }
+}
+
+/// Parses the `when` condition of `context`, which may only use the built-in options and `prompts`
+pub fn parse_when(
+    source: &str,
+    context: &str,
+    prompts: &[Prompt],
+) -> std::result::Result<Expr, String> {
+    let expr =
+        Expr::parse(source).map_err(|err| format!("`{}.when` is invalid: {}", context, err))?;
+    for variable in expr.variables() {
+        let known = BUILTIN_VARIABLES.contains(&variable)
+            || prompts.iter().any(|prompt| prompt.name == variable);
+        if !known {
+            return Err(format!(
+                "`{}.when` uses `{}`, which is not a built-in option or an earlier prompt",
+                context, variable
+            ));
+        }
+    }
+    Ok(expr)
+}
+
+/// Parses `--set NAME=VALUE` flags against the stack's prompts
+pub fn parse_set_flags(prompts: &[Prompt], flags: &[String]) -> Result<Variables> {
+    let mut answers = Variables::new();
+    for flag in flags {
+        let (name, text) = flag.split_once('=').ok_or_else(|| {
+            Error::Config(format!("--set {} must have the form NAME=VALUE", flag))
+        })?;
+        let prompt = find(prompts, name)?;
+        let value = prompt
+            .parse_value(text)
+            .map_err(|err| Error::Config(format!("--set {}: {}", flag, err)))?;
+        answers.insert(name.to_string(), value);
+    }
+    Ok(answers)
+}
+
+/// Answers every prompt whose `when` holds, in order. Supplied answers are used as is, the rest are
+/// asked for or take their defaults when prompts are disabled. Each answer is added to `variables`
+/// so later conditions can use it, and the prompt answers are returned.
+pub fn answer(
+    prompts: &[Prompt],
+    supplied: &Variables,
+    variables: &mut Variables,
+    interactive: bool,
+) -> Result<Variables> {
+    for name in supplied.keys() {
+        find(prompts, name)?;
+    }
+
+    let mut answers = Variables::new();
+    for prompt in prompts {
+        if let Some(when) = &prompt.when {
+            if !when.evaluate(variables) {
+                continue;
+            }
+        }
+        let value = match supplied.get(&prompt.name) {
+            Some(value) => {
+                prompt.check(value).map_err(|err| {
+                    Error::Config(format!("Invalid answer for `{}`: {}", prompt.name, err))
+                })?;
+                value.clone()
+            }
+            None if interactive => prompt.ask()?,
+            None => prompt.default_value(),
+        };
+        variables.insert(prompt.name.clone(), value.clone());
+        answers.insert(prompt.name.clone(), value);
+    }
+    Ok(answers)
+}
+
+fn find<'a>(prompts: &'a [Prompt], name: &str) -> Result<&'a Prompt> {
+    prompts
+        .iter()
+        .find(|prompt| prompt.name == name)
+        .ok_or_else(|| {
+            let names = prompts
+                .iter()
+                .map(|prompt| prompt.name.as_str())
+                .collect::<Vec<_>>();
+            Error::Config(match names.is_empty() {
+                true => format!("Unknown prompt `{}`, this stack declares no prompts", name),
+                false => format!(
+                    "Unknown prompt `{}`, this stack's prompts are: {}",
+                    name,
+                    names.join(", ")
+                ),
+            })
+        })
+}
+
+fn is_identifier(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with(|c: char| c.is_ascii_digit())
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::manifest::Manifest;
+
+    fn parse_prompts(source: &str) -> std::result::Result<Vec<Prompt>, String> {
+        let manifest = toml::from_str::<Manifest>(source).unwrap();
+        let mut prompts: Vec<Prompt> = vec![];
+        for (name, entry) in &manifest.prompts {
+            let prompt = Prompt::parse(name, entry, &prompts)?;
+            prompts.push(prompt);
+        }
+        Ok(prompts)
+    }
+
+    #[test]
+    fn answer_prompts_without_input() {
+        let prompts = parse_prompts(
+            r#"
+            [prompts.auth]
+            type = "bool"
+            default = true
+
+            [prompts.provider]
+            type = "select"
+            options = ["github", "google"]
+            when = "auth && db == \"postgres\""
+
+            [prompts.port]
+            type = "int"
+            default = 8080
+            "#,
+        )
+        .expect("Error parsing prompts");
+
+        let supplied = parse_set_flags(&prompts, &["port=3000".to_string()]).unwrap();
+        let mut variables = Variables::new();
+        variables.insert("db".to_string(), Value::String("sqlite".to_string()));
+        let answers = answer(&prompts, &supplied, &mut variables, false).unwrap();
+
+        assert_eq!(answers.get("auth"), Some(&Value::Boolean(true)));
+        assert_eq!(answers.get("provider"), None);
+        assert_eq!(answers.get("port"), Some(&Value::Integer(3000)));
+    }
+
+    #[test]
+    fn reject_invalid_prompts() {
+        let error = |source: &str| parse_prompts(source).unwrap_err();
+        assert_eq!(
+            error("[prompts.auth]\ntype = \"select\"\noptions = [\"a\"]\ndefault = \"b\""),
+            "`prompts.auth.default` is invalid: `b` is not one of a"
+        );
+        assert_eq!(
+            error("[prompts.auth]\ntype = \"bool\"\nwhen = \"later\"\n[prompts.later]\ntype = \"bool\""),
+            "`prompts.auth.when` uses `later`, which is not a built-in option or an earlier prompt"
+        );
+        assert_eq!(
+            error("[prompts.db]\ntype = \"bool\""),
+            "`prompts.db`: `db` is a built-in variable, pick another name"
+        );
+    }
+}
@@ -0,0 +1,615 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    path::{Path, PathBuf},
+};
+
+use schemars::{
+    gen::SchemaSettings,
+    schema::{InstanceType, Schema, SchemaObject, SingleOrVec},
+    JsonSchema, Map,
+};
+use serde::{
+    de::{self, MapAccess, SeqAccess, Visitor},
+    Deserialize, Deserializer,
+};
+use toml::{Spanned, Table, Value};
+
+/// A schema violation and where it is: the file, and the line and column in it, both 1 based
+#[derive(Debug, Clone, PartialEq)]
+pub struct SchemaError {
+    pub path: PathBuf,
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl SchemaError {
+    /// Another error at the same place
+    pub fn with_message(&self, message: String) -> Self {
+        SchemaError {
+            message,
+            ..self.clone()
+        }
+    }
+}
+
+/// A TOML file parsed once, remembering where each of its values is so they can be checked
+/// against the schema of the serde types they are read as
+pub struct Document<'a> {
+    path: &'a Path,
+    source: &'a str,
+    root: Node,
+    /// Byte offset of every value, keyed by a path like `deps.cargo[1].when`
+    positions: HashMap<String, usize>,
+}
+
+impl<'a> Document<'a> {
+    /// Parses `source`, read from the file at `path`
+    pub fn parse(path: &'a Path, source: &'a str) -> Result<Self, toml::de::Error> {
+        let root = toml::from_str::<Node>(source)?;
+        let mut positions = HashMap::from([(String::new(), 0)]);
+        record_positions(source, &root, "", &mut positions);
+        Ok(Document {
+            path,
+            source,
+            root,
+            positions,
+        })
+    }
+
+    /// Checks the file against the schema of `T`, returning every violation in the order they
+    /// appear
+    pub fn check<T: JsonSchema>(&self) -> Vec<SchemaError> {
+        let schema = SchemaSettings::draft07()
+            .with(|settings| settings.option_add_null_type = false)
+            .into_generator()
+            .into_root_schema_for::<T>();
+        let root = Schema::Object(schema.schema);
+        let mut checker = Checker {
+            document: self,
+            definitions: &schema.definitions,
+            errors: vec![],
+        };
+        checker.check(&self.root, &root, "");
+        checker
+            .errors
+            .sort_by_key(|error| (error.line, error.column));
+        checker.errors
+    }
+
+    /// An error at the value found under `path`, or at the start of the file if there is none
+    pub fn error(&self, path: &str, message: String) -> SchemaError {
+        let offset = self.positions.get(path).copied().unwrap_or_default();
+        self.error_at(offset, message)
+    }
+
+    /// An error at the byte `offset` of the file
+    fn error_at(&self, offset: usize, message: String) -> SchemaError {
+        let before = &self.source[..offset.min(self.source.len())];
+        SchemaError {
+            path: self.path.to_path_buf(),
+            line: before.matches('\n').count() + 1,
+            column: before.chars().rev().take_while(|c| *c != '\n').count() + 1,
+            message,
+        }
+    }
+
+    pub fn to_value(&self) -> Value {
+        self.root.to_value()
+    }
+}
+
+/// A TOML value with the position of every key and array item in it. toml doesn't know the
+/// position of every value, e.g. not of the tables implied by `[deps.cargo]`, so values in
+/// tables are located from their key.
+#[derive(Debug)]
+enum Node {
+    Table(Vec<(Spanned<String>, Node)>),
+    Array(Vec<Spanned<Node>>),
+    Value(Value),
+}
+
+impl Node {
+    fn to_value(&self) -> Value {
+        match self {
+            Node::Table(entries) => Value::Table(
+                entries
+                    .iter()
+                    .map(|(key, value)| (key.get_ref().clone(), value.to_value()))
+                    .collect::<Table>(),
+            ),
+            Node::Array(items) => {
+                Value::Array(items.iter().map(|item| item.get_ref().to_value()).collect())
+            }
+            Node::Value(value) => value.clone(),
+        }
+    }
+
+    fn instance_type(&self) -> Option<InstanceType> {
+        match self {
+            Node::Table(_) => Some(InstanceType::Object),
+            Node::Array(_) => Some(InstanceType::Array),
+            Node::Value(Value::String(_)) => Some(InstanceType::String),
+            Node::Value(Value::Integer(_)) => Some(InstanceType::Integer),
+            Node::Value(Value::Float(_)) => Some(InstanceType::Number),
+            Node::Value(Value::Boolean(_)) => Some(InstanceType::Boolean),
+            Node::Value(_) => None,
+        }
+    }
+
+    /// Whether the node is one of `types`, an integer is also a number
+    fn is_any_of(&self, types: &SingleOrVec<InstanceType>) -> bool {
+        match self.instance_type() {
+            Some(InstanceType::Integer) => {
+                types.contains(&InstanceType::Integer) || types.contains(&InstanceType::Number)
+            }
+            Some(instance_type) => types.contains(&instance_type),
+            None => false,
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Node {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(NodeVisitor)
+    }
+}
+
+struct NodeVisitor;
+
+impl<'de> Visitor<'de> for NodeVisitor {
+    type Value = Node;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a TOML value")
+    }
+
+    fn visit_bool<E>(self, value: bool) -> Result<Node, E> {
+        Ok(Node::Value(Value::Boolean(value)))
+    }
+
+    fn visit_i64<E>(self, value: i64) -> Result<Node, E> {
+        Ok(Node::Value(Value::Integer(value)))
+    }
+
+    fn visit_u64<E: de::Error>(self, value: u64) -> Result<Node, E> {
+        let value = i64::try_from(value).map_err(E::custom)?;
+        Ok(Node::Value(Value::Integer(value)))
+    }
+
+    fn visit_f64<E>(self, value: f64) -> Result<Node, E> {
+        Ok(Node::Value(Value::Float(value)))
+    }
+
+    fn visit_str<E>(self, value: &str) -> Result<Node, E> {
+        Ok(Node::Value(Value::String(value.to_string())))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Node, A::Error> {
+        let mut items = vec![];
+        while let Some(item) = seq.next_element()? {
+            items.push(item);
+        }
+        Ok(Node::Array(items))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Node, A::Error> {
+        let mut entries = vec![];
+        loop {
+            let key = match map.next_key::<Spanned<String>>() {
+                Ok(Some(key)) => key,
+                Ok(None) => return Ok(Node::Table(entries)),
+                // toml passes a datetime as a single entry table, the only one whose key has no
+                // position in the file
+                Err(_) if entries.is_empty() => {
+                    let datetime = map.next_value::<String>()?;
+                    let datetime = datetime.parse().map_err(de::Error::custom)?;
+                    return Ok(Node::Value(Value::Datetime(datetime)));
+                }
+                Err(err) => return Err(err),
+            };
+            entries.push((key, map.next_value()?));
+        }
+    }
+}
+
+/// Records where the values in `node`, found under `path`, start
+fn record_positions(source: &str, node: &Node, path: &str, positions: &mut HashMap<String, usize>) {
+    match node {
+        Node::Table(entries) => {
+            for (key, value) in entries {
+                let value_path = join(path, key.get_ref());
+                positions.insert(value_path.clone(), value_start(source, key));
+                record_positions(source, value, &value_path, positions);
+            }
+        }
+        Node::Array(items) => {
+            for (index, item) in items.iter().enumerate() {
+                let item_path = format!("{}[{}]", path, index);
+                positions.insert(item_path.clone(), item.span().start);
+                record_positions(source, item.get_ref(), &item_path, positions);
+            }
+        }
+        Node::Value(_) => {}
+    }
+}
+
+/// Where the value of `key` starts: after the `=` of a `key = value` pair, and at the key itself
+/// for tables declared by a `[header]` or implied by dotted keys
+fn value_start(source: &str, key: &Spanned<String>) -> usize {
+    let rest = source.get(key.span().end..).unwrap_or_default();
+    let rest = rest
+        .trim_start_matches(['"', '\''])
+        .trim_start_matches([' ', '\t']);
+    match rest.strip_prefix('=') {
+        Some(value) => source.len() - value.trim_start_matches([' ', '\t']).len(),
+        None => key.span().start,
+    }
+}
+
+struct Checker<'a> {
+    document: &'a Document<'a>,
+    definitions: &'a Map<String, Schema>,
+    errors: Vec<SchemaError>,
+}
+
+impl<'a> Checker<'a> {
+    /// Checks `node`, found under the dotted `path`, against `schema`
+    fn check(&mut self, node: &Node, schema: &'a Schema, path: &str) {
+        let Some(schema) = resolve(self.definitions, schema) else {
+            return;
+        };
+        if let Some(names) = enum_names(self.definitions, schema) {
+            match node {
+                Node::Value(Value::String(value)) if names.contains(&value.as_str()) => {}
+                Node::Value(Value::String(value)) => {
+                    self.error(path, not_one_of(value, path, &names))
+                }
+                _ => self.error(path, format!("`{}` must be a string", path)),
+            }
+            return;
+        }
+        if let Some(subschemas) = &schema.subschemas {
+            for all_of in subschemas.all_of.iter().flatten() {
+                self.check(node, all_of, path);
+            }
+            if let Some(any_of) = subschemas.any_of.as_ref().or(subschemas.one_of.as_ref()) {
+                match any_of.iter().find(|branch| self.has_type_of(node, branch)) {
+                    Some(branch) => self.check(node, branch, path),
+                    None => {
+                        let expected = self.describe_any(any_of);
+                        self.error(path, format!("`{}` must be {}", path, expected));
+                    }
+                }
+            }
+        }
+        if let Some(types) = &schema.instance_type {
+            if !node.is_any_of(types) {
+                let expected = self.describe_object(schema);
+                self.error(path, format!("`{}` must be {}", path, expected));
+                return;
+            }
+        }
+        match node {
+            Node::Table(entries) => {
+                let Some(object) = &schema.object else {
+                    return;
+                };
+                for (key, value) in entries {
+                    let value_path = join(path, key.get_ref());
+                    match (
+                        object.properties.get(key.get_ref()),
+                        object.additional_properties.as_deref(),
+                    ) {
+                        (Some(property), _) => self.check(value, property, &value_path),
+                        (None, Some(Schema::Bool(false))) => {
+                            let names = object.properties.keys().map(String::as_str);
+                            let message =
+                                unknown_key(key.get_ref(), path, &names.collect::<Vec<_>>());
+                            let error = self.document.error_at(key.span().start, message);
+                            self.errors.push(error);
+                        }
+                        (None, Some(additional)) => self.check(value, additional, &value_path),
+                        (None, None) => {}
+                    }
+                }
+                for name in &object.required {
+                    if !entries.iter().any(|(key, _)| key.get_ref() == name) {
+                        self.error(path, format!("missing key `{}`{}", name, location(path)));
+                    }
+                }
+            }
+            Node::Array(items) => {
+                let items_schema = schema.array.as_ref().and_then(|array| array.items.as_ref());
+                if let Some(SingleOrVec::Single(item_schema)) = items_schema {
+                    for (index, item) in items.iter().enumerate() {
+                        let item_path = format!("{}[{}]", path, index);
+                        self.check(item.get_ref(), item_schema, &item_path);
+                    }
+                }
+            }
+            Node::Value(_) => {}
+        }
+    }
+
+    /// Whether `node` has the outer shape of `schema`, used to pick a branch of `anyOf`
+    fn has_type_of(&self, node: &Node, schema: &Schema) -> bool {
+        match resolve(self.definitions, schema).and_then(|schema| schema.instance_type.as_ref()) {
+            Some(types) => node.is_any_of(types),
+            None => true,
+        }
+    }
+
+    fn describe(&self, schema: &Schema) -> String {
+        match resolve(self.definitions, schema) {
+            Some(schema) => self.describe_object(schema),
+            None => "a value".to_string(),
+        }
+    }
+
+    fn describe_object(&self, schema: &SchemaObject) -> String {
+        if enum_names(self.definitions, schema).is_some() {
+            return "a string".to_string();
+        }
+        if let Some(subschemas) = &schema.subschemas {
+            if let Some(any_of) = subschemas.any_of.as_ref().or(subschemas.one_of.as_ref()) {
+                return self.describe_any(any_of);
+            }
+            if let Some([all_of]) = subschemas.all_of.as_deref() {
+                return self.describe(all_of);
+            }
+        }
+        match &schema.instance_type {
+            Some(SingleOrVec::Single(instance_type)) => match **instance_type {
+                InstanceType::String => "a string".to_string(),
+                InstanceType::Boolean => "a boolean".to_string(),
+                InstanceType::Integer => "an integer".to_string(),
+                InstanceType::Number => "a number".to_string(),
+                InstanceType::Object => "a table".to_string(),
+                InstanceType::Array => {
+                    let items = schema.array.as_ref().and_then(|array| array.items.as_ref());
+                    match items {
+                        Some(SingleOrVec::Single(items)) => {
+                            format!("an array of {}", self.describe_items(items))
+                        }
+                        _ => "an array".to_string(),
+                    }
+                }
+                InstanceType::Null => "nothing".to_string(),
+            },
+            _ => "a value".to_string(),
+        }
+    }
+
+    fn describe_any(&self, schemas: &[Schema]) -> String {
+        let descriptions = schemas
+            .iter()
+            .map(|schema| self.describe(schema))
+            .collect::<Vec<_>>();
+        descriptions.join(" or ")
+    }
+
+    /// The plural of what an array holds, e.g. `strings`
+    fn describe_items(&self, schema: &Schema) -> &'static str {
+        let Some(schema) = resolve(self.definitions, schema) else {
+            return "values";
+        };
+        if enum_names(self.definitions, schema).is_some() {
+            return "strings";
+        }
+        match &schema.instance_type {
+            Some(SingleOrVec::Single(instance_type)) => match **instance_type {
+                InstanceType::String => "strings",
+                InstanceType::Boolean => "booleans",
+                InstanceType::Integer => "integers",
+                InstanceType::Number => "numbers",
+                InstanceType::Object => "tables",
+                InstanceType::Array => "arrays",
+                InstanceType::Null => "values",
+            },
+            _ => "values",
+        }
+    }
+
+    fn error(&mut self, path: &str, message: String) {
+        self.errors.push(self.document.error(path, message));
+    }
+}
+
+/// Follows `$ref`s to the schema they name, `None` for a schema accepting anything
+fn resolve<'s>(
+    definitions: &'s Map<String, Schema>,
+    schema: &'s Schema,
+) -> Option<&'s SchemaObject> {
+    let Schema::Object(object) = schema else {
+        return None;
+    };
+    match object.reference.as_deref() {
+        Some(reference) => {
+            let name = reference.strip_prefix("#/definitions/")?;
+            resolve(definitions, definitions.get(name)?)
+        }
+        None => Some(object),
+    }
+}
+
+/// The values of an enum of strings, whose variants may also be split into `oneOf` branches
+/// when some are documented
+fn enum_names<'s>(
+    definitions: &'s Map<String, Schema>,
+    schema: &'s SchemaObject,
+) -> Option<Vec<&'s str>> {
+    if let Some(values) = &schema.enum_values {
+        return values.iter().map(|value| value.as_str()).collect();
+    }
+    let subschemas = schema.subschemas.as_ref()?;
+    let branches = subschemas.any_of.as_ref().or(subschemas.one_of.as_ref())?;
+    let mut names = vec![];
+    for branch in branches {
+        names.extend(enum_names(definitions, resolve(definitions, branch)?)?);
+    }
+    Some(names)
+}
+
+fn unknown_key(key: &str, path: &str, names: &[&str]) -> String {
+    match suggest(key, names) {
+        Some(name) => format!(
+            "unknown key `{}`{}, did you mean `{}`?",
+            key,
+            location(path),
+            name
+        ),
+        None => format!(
+            "unknown key `{}`{}, expected one of {}",
+            key,
+            location(path),
+            names.join(", ")
+        ),
+    }
+}
+
+fn not_one_of(value: &str, path: &str, names: &[&str]) -> String {
+    let message = format!(
+        "`{}` must be one of {}, found `{}`",
+        path,
+        names.join(", "),
+        value
+    );
+    match suggest(value, names) {
+        Some(name) => format!("{}, did you mean `{}`?", message, name),
+        None => message,
+    }
+}
+
+/// Names the table a key is in, nothing for the top level
+fn location(path: &str) -> String {
+    match path.is_empty() {
+        true => String::new(),
+        false => format!(" in `{}`", path),
+    }
+}
+
+fn join(path: &str, key: &str) -> String {
+    match path.is_empty() {
+        true => key.to_string(),
+        false => format!("{}.{}", path, key),
+    }
+}
+
+/// The known key closest to a misspelled one, allowing a typo for every three characters
+fn suggest<'a>(key: &str, names: &[&'a str]) -> Option<&'a str> {
+    let allowed = (key.chars().count() / 3).max(1);
+    names
+        .iter()
+        .map(|name| (strsim::osa_distance(key, name), *name))
+        .filter(|(distance, _)| *distance <= allowed)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, name)| name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::manifest::{Group, Manifest};
+
+    fn check<T: JsonSchema>(source: &str) -> Vec<String> {
+        let document = Document::parse(Path::new("test.toml"), source).expect("Error parsing TOML");
+        document
+            .check::<T>()
+            .iter()
+            .map(|error| format!("{}:{}: {}", error.line, error.column, error.message))
+            .collect()
+    }
+
+    #[test]
+    fn report_unknown_keys_and_types() {
+        let source = r#"title = "api"
+web = "yes"
+
+[[deps.cargo]]
+name = "axum"
+feature = ["macros"]
+
+[[deps.cargo]]
+name = "tokio"
+versoin = "1"
+dev = 1
+
+[deps.nmp]
+"#;
+        assert_eq!(
+            check::<Manifest>(source),
+            [
+                "2:7: `web` must be a boolean",
+                "6:1: unknown key `feature` in `deps.cargo[0]`, did you mean `features`?",
+                "10:1: unknown key `versoin` in `deps.cargo[1]`, did you mean `version`?",
+                "11:7: `deps.cargo[1].dev` must be a boolean",
+                "13:7: unknown key `nmp` in `deps`, did you mean `npm`?",
+            ]
+        );
+    }
+
+    #[test]
+    fn locate_errors_in_implied_tables_and_datetimes() {
+        let source = r#"deps.cargo = [{ name = "axum", dev = "yes" }]
+"order" = 1979-05-27
+languages = ["rust", 1]
+
+[prompts.ready]
+type = "bool"
+default = 07:32:00
+
+[addons.auth.deps.nmp]
+"#;
+        assert_eq!(
+            check::<Manifest>(source),
+            [
+                "1:38: `deps.cargo[0].dev` must be a boolean",
+                "2:11: `order` must be an integer",
+                "3:22: `languages[1]` must be a string",
+                "9:19: unknown key `nmp` in `addons.auth.deps`, did you mean `npm`?",
+            ]
+        );
+    }
+
+    #[test]
+    fn report_missing_keys_and_unknown_values() {
+        assert_eq!(
+            check::<Group>("description = \"web\"\n"),
+            ["1:1: missing key `deps`"]
+        );
+
+        let source = r#"languages = ["rsut"]
+
+[[deps.npm]]
+version = "1"
+
+[prompts.auth]
+type = "boolean"
+"#;
+        assert_eq!(
+            check::<Manifest>(source),
+            [
+                "1:14: `languages[0]` must be one of rust, javascript, typescript, found `rsut`, did you mean `rust`?",
+                "3:1: missing key `name` in `deps.npm[0]`",
+                "7:8: `prompts.auth.type` must be one of bool, select, multiselect, string, int, found `boolean`",
+            ]
+        );
+    }
+
+    #[test]
+    fn accept_bundled_manifests() {
+        let templates_dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("templates");
+        for stack in ["tsapi", "rsapi", "rscli", "tscli"] {
+            let path = templates_dir.join(stack).join("stack_template.toml");
+            let source = std::fs::read_to_string(&path).expect("Error reading manifest");
+            assert_eq!(
+                check::<Manifest>(&source),
+                [] as [String; 0],
+                "{}",
+                path.display()
+            );
+        }
+    }
+}
@@ -0,0 +1,249 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    addons::Addon,
+    config::{Language, PackageManager},
+    error::{Context, Error, Result},
+    linters::{Linter, LinterSpec},
+    prompts::Prompt,
+    toml_parser::TomlTemplate,
+};
+
+/// File every stack folder in the templates directory must contain
+pub const MANIFEST: &str = "stack_template.toml";
+
+/// A stack discovered in the templates directory, named after its folder
+#[derive(Debug, Clone)]
+pub struct Stack {
+    pub name: String,
+    pub label: String,
+    pub description: String,
+    /// Position in the stack list, stacks without one are listed last
+    pub order: Option<i64>,
+    /// Offers the SPA, template engine and container options
+    pub web: bool,
+    pub languages: Vec<Language>,
+    pub package_managers: Vec<PackageManager>,
+    /// Linters installed when their `when` condition holds for the user's answers
+    pub linters: Vec<LinterSpec>,
+    /// Questions declared by the stack, asked after the built-in ones
+    pub prompts: Vec<Prompt>,
+    /// Optional features the user picks from
+    pub addons: Vec<Addon>,
+    pub template_dir: PathBuf,
+    /// Template folders the stack's files come from, the stacks it extends first
+    pub template_dirs: Vec<PathBuf>,
+    /// The manifest merged with the stacks it extends and its groups, parsed once when the
+    /// stack is loaded
+    pub template: TomlTemplate,
+}
+
+impl Stack {
+    pub fn load(template_dir: &Path) -> Result<Self> {
+        let name = template_dir
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .ok_or_else(|| {
+                Error::Config(format!("Invalid stack folder {}", template_dir.display()))
+            })?;
+        let toml = TomlTemplate::new(&template_dir.join(MANIFEST))?;
+
+        // undeclared package managers follow from the deps, languages from the package managers
+        // and linters from the languages
+        let package_managers = match toml.get_package_managers() {
+            Some(package_managers) => package_managers.to_vec(),
+            None => PackageManager::NAMES
+                .iter()
+                .filter(|name| matches!(toml.get_dependencies().get(**name), Some(Some(_))))
+                .filter_map(|name| PackageManager::from_name(name))
+                .collect(),
+        };
+        let languages = match toml.get_languages() {
+            Some(languages) => languages.to_vec(),
+            None => {
+                let mut languages = vec![];
+                if package_managers.contains(&PackageManager::Cargo) {
+                    languages.push(Language::Rust);
+                }
+                if package_managers.contains(&PackageManager::Npm) {
+                    languages.append(&mut vec![Language::TypeScript, Language::JavaScript]);
+                }
+                languages
+            }
+        };
+        let linters = match toml.get_linters() {
+            Some(linters) => linters.to_vec(),
+            None => {
+                let mut linters = vec![];
+                if languages.contains(&Language::TypeScript) {
+                    linters.push(Linter::ESLint);
+                }
+                if languages.contains(&Language::Rust) {
+                    linters.push(Linter::Clippy);
+                }
+                linters
+                    .into_iter()
+                    .map(|linter| LinterSpec { linter, when: None })
+                    .collect()
+            }
+        };
+
+        Ok(Self {
+            label: toml
+                .get_label()
+                .or(toml.get_title())
+                .unwrap_or(&name)
+                .to_string(),
+            description: toml.get_description().unwrap_or_default().to_string(),
+            order: toml.get_order(),
+            web: toml.is_web(),
+            languages,
+            package_managers,
+            linters,
+            prompts: toml.get_prompts().to_vec(),
+            addons: toml.get_addons().to_vec(),
+            template_dir: template_dir.to_path_buf(),
+            template_dirs: toml.get_layers().to_vec(),
+            template: toml,
+            name,
+        })
+    }
+
+    pub fn has_language(&self, language: &Language) -> bool {
+        self.languages.contains(language)
+    }
+}
+
+/// The stacks of a templates directory, and the stack folders whose manifest fails to load
+#[derive(Debug, Default)]
+pub struct Discovered {
+    pub stacks: Vec<Stack>,
+    /// Folder names paired with why they can't be loaded
+    pub invalid: Vec<(String, Error)>,
+}
+
+impl Discovered {
+    /// Warns about every stack that was skipped, on stderr so listings can still be piped
+    pub fn warn_invalid(&self) {
+        for (name, err) in &self.invalid {
+            eprintln!("warning: skipping invalid stack `{}`: {}", name, err);
+        }
+    }
+}
+
+/// Every folder in `templates_dir` with a stack_template.toml, sorted by their `order`, then label.
+/// A stack that fails to load is set aside instead of failing the others.
+pub fn discover(templates_dir: &Path) -> Result<Discovered> {
+    let entries = fs::read_dir(templates_dir).with_context(|| {
+        format!(
+            "Failed to read templates directory {}",
+            templates_dir.display()
+        )
+    })?;
+    let mut discovered = Discovered::default();
+    for entry in entries.flatten() {
+        let dir = entry.path();
+        if !dir.join(MANIFEST).is_file() {
+            continue;
+        }
+        match Stack::load(&dir) {
+            Ok(stack) => discovered.stacks.push(stack),
+            Err(err) => discovered
+                .invalid
+                .push((entry.file_name().to_string_lossy().to_string(), err)),
+        }
+    }
+    discovered.stacks.sort_by(|a, b| {
+        (a.order.is_none(), a.order, &a.label).cmp(&(b.order.is_none(), b.order, &b.label))
+    });
+    discovered.invalid.sort_by(|a, b| a.0.cmp(&b.0));
+    Ok(discovered)
+}
+
+/// Loads the stack called `name` from `templates_dir`
+pub fn find(templates_dir: &Path, name: &str) -> Result<Stack> {
+    let template_dir = templates_dir.join(name);
+    let is_folder_name = !name.starts_with('.') && !name.contains(['/', '\\']);
+    if is_folder_name && template_dir.join(MANIFEST).is_file() {
+        return Stack::load(&template_dir);
+    }
+    let available = discover(templates_dir)?
+        .stacks
+        .into_iter()
+        .map(|stack| stack.name)
+        .collect::<Vec<_>>();
+    Err(Error::Config(format!(
+        "Unknown stack `{}`, available stacks: {}",
+        name,
+        available.join(", ")
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn discover_bundled_stacks() {
+        let templates_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("templates");
+        let stacks = discover(&templates_dir)
+            .expect("Error discovering stacks")
+            .stacks;
+
+        let names = stacks
+            .iter()
+            .map(|stack| stack.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["tsapi", "rsapi", "rscli", "tscli"]);
+        assert!(stacks[1].web);
+        assert!(stacks[1].has_language(&Language::Rust));
+        assert_eq!(stacks[3].label, "TypeScript CLI Tool");
+    }
+
+    #[test]
+    fn find_rejects_unknown_stack() {
+        let templates_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("templates");
+        assert!(find(&templates_dir, "rsapi").is_ok());
+        assert!(matches!(
+            find(&templates_dir, "../templates/rsapi"),
+            Err(Error::Config(_))
+        ));
+    }
+
+    #[test]
+    fn discover_skips_invalid_stacks() {
+        let scratch = tempfile::tempdir().expect("Error creating templates dir");
+        for name in ["good", "broken"] {
+            fs::create_dir(scratch.path().join(name)).expect("Error creating stack");
+        }
+        fs::write(
+            scratch.path().join("good").join(MANIFEST),
+            "[[deps.cargo]]\nname = \"tokio\"\n",
+        )
+        .expect("Error writing manifest");
+        fs::write(
+            scratch.path().join("broken").join(MANIFEST),
+            "[[deps.npm]]\nname = \"x\"\nalias = \"npm:\"\n",
+        )
+        .expect("Error writing manifest");
+
+        let discovered = discover(scratch.path()).expect("Error discovering stacks");
+        assert_eq!(discovered.stacks.len(), 1);
+        assert_eq!(discovered.stacks[0].name, "good");
+        assert_eq!(discovered.invalid.len(), 1);
+        assert_eq!(discovered.invalid[0].0, "broken");
+
+        // the broken stack still fails when it is asked for by name
+        assert!(find(scratch.path(), "broken").is_err());
+        match find(scratch.path(), "missing") {
+            Err(Error::Config(message)) => assert!(message.ends_with("available stacks: good")),
+            other => panic!(
+                "Unknown stack not reported: {:?}",
+                other.map(|stack| stack.name)
+            ),
+        }
+    }
+}
@@ -0,0 +1,201 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    error::{Context, Error, Result},
+    stack::MANIFEST,
+    toml_parser::GROUPS_DIR,
+};
+
+/// Commented manifest of a new stack, `{name}` is replaced with the stack's name
+const MANIFEST_TEMPLATE: &str = r#"# Stack manifest, see the "Customization" section of the germinate README.
+# Check the stack with `germinate template validate {name}`.
+
+# General Config
+title = "{name}"
+label = "{name}"                 # shown in the stack prompt, defaults to title
+description = ""                 # shown next to the label in `germinate list`
+# order = 5                      # position in the stack list, stacks without one are listed last
+web = true                       # offers the SPA, template engine and container options
+# extends = "rsapi"              # inherit the deps, scripts and files of another stack
+
+# Tooling
+# Left out keys are inferred: package managers from the deps tables, languages from the
+# package managers and linters from the languages.
+languages = ["rust"]             # rust, typescript, javascript
+package_managers = ["cargo"]     # cargo, npm
+linters = ["clippy"]             # clippy, eslint, stylelint
+# subfolders = { src = { routes = {} } }  # folders created in every project, `{}` is a leaf
+
+# Shared dependencies from templates/_groups/<group>.toml
+# groups = ["serde"]
+
+# Dependencies, one table per package, at least one is required
+[[deps.cargo]]
+name = "tokio"
+# version = "1"                  # defaults to the latest version
+features = ["full"]
+# dev = false                    # install as a dev dependency
+# when = "db == 'postgres'"      # only install when this holds for the user's answers
+# then = [["cargo", "check"]]    # commands run in the project once it is installed
+# git = "https://github.com/tokio-rs/tokio"  # cargo only: fetch from git, pinned with
+# branch = "master"              # branch, tag or rev; or `path = "../tokio"` for a local crate
+# default-features = false       # cargo only, like optional, rename, package, build and target
+# npm deps take peer, optional, exact, alias, git, tarball, file and registry instead, see the README
+
+# Scripts added to the project's package manager, npm only
+# [scripts.npm]
+# dev = "bun run --watch src/index.ts"
+
+# Questions asked after the built-in ones, their answers are variables of the docker templates
+[prompts.port]
+type = "int"                     # bool, select, multiselect, string or int
+message = "Which port should the app listen on?"
+default = 3000
+
+# Optional features the user picks from, their files are copied from addons/<name>
+# [addons.tracing]
+# label = "Tracing"
+# description = "Structured logging with tracing"
+#
+# [[addons.tracing.deps.cargo]]
+# name = "tracing"
+"#;
+
+/// Sample docker template showing the variables available to the files of `docker/`
+const COMPOSE_TEMPLATE: &str = r#"# Rendered with Handlebars when the user asks for containers, then moved to the project root.
+# Variables: app_name, deps_name, database, postgres, mongo, sqlite and the stack's prompt answers.
+services:
+  web:
+    # replace with `build:` pointing at a Dockerfile in docker/app
+    image: alpine:3
+    restart: unless-stopped
+    ports:
+      - {{ port }}:{{ port }}
+    container_name: {{ app_name }}_web
+{{#if database}}
+    depends_on:
+      - db
+{{/if}}
+{{#if postgres}}
+
+  db:
+    image: postgres:16
+    restart: always
+    environment:
+      POSTGRES_PASSWORD: password
+      POSTGRES_USER: dbuser
+      POSTGRES_DB: database
+    ports:
+      - 5432:5432
+{{/if}}
+{{#if mongo}}
+
+  db:
+    image: mongo:4.4.6
+    restart: always
+    ports:
+      - 27017:27017
+{{/if}}
+{{#if sqlite}}
+
+  db:
+    image: keinos/sqlite3:latest
+    restart: always
+{{/if}}
+"#;
+
+/// Creates `templates_dir/<name>` with a commented manifest, empty `before_install` and
+/// `after_install` folders and a sample docker template, returning the stack's folder
+pub fn new_stack(name: &str, templates_dir: &Path) -> Result<PathBuf> {
+    let is_folder_name = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        && !name.starts_with(['-', '_']);
+    if !is_folder_name || name == GROUPS_DIR {
+        return Err(Error::Config(format!(
+            "Invalid stack name `{}`, use letters, digits, `-` and `_`, starting with a letter or digit",
+            name
+        )));
+    }
+    let stack_dir = templates_dir.join(name);
+    if stack_dir.exists() {
+        return Err(Error::Config(format!(
+            "{} already exists, pick another name or remove it first",
+            stack_dir.display()
+        )));
+    }
+
+    println!("->> Creating stack template {}", stack_dir.display());
+    for folder in ["before_install", "after_install", "docker"] {
+        let dir = stack_dir.join(folder);
+        fs::create_dir_all(&dir)
+            .with_context(|| format!("Failed to create directory {}", dir.display()))?;
+    }
+    let files = [
+        (
+            PathBuf::from(MANIFEST),
+            MANIFEST_TEMPLATE.replace("{name}", name),
+        ),
+        (
+            Path::new("docker").join("docker-compose.yml"),
+            COMPOSE_TEMPLATE.to_string(),
+        ),
+    ];
+    for (file, contents) in &files {
+        let path = stack_dir.join(file);
+        fs::write(&path, contents)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        println!("  created {}", file.display());
+    }
+    println!(
+        "->> Edit {} to add dependencies, then check it with `germinate template validate {}`",
+        stack_dir.join(MANIFEST).display(),
+        name
+    );
+    Ok(stack_dir)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{stack::Stack, validate};
+
+    #[test]
+    fn new_stack_is_valid() {
+        let scratch = tempfile::tempdir().expect("Error creating templates dir");
+        let templates_dir = scratch.path();
+
+        let stack_dir = new_stack("my-stack", templates_dir).expect("Error creating stack");
+        assert!(stack_dir.join("before_install").is_dir());
+        assert!(stack_dir.join("after_install").is_dir());
+        let stack = Stack::load(&stack_dir).expect("Error loading new stack");
+        assert_eq!(stack.label, "my-stack");
+        assert!(stack.web);
+        validate::validate_stack(&stack_dir.to_string_lossy(), || unreachable!())
+            .expect("New stack fails validation");
+
+        // the commented examples are valid once uncommented
+        let manifest = stack_dir.join(MANIFEST);
+        let source = fs::read_to_string(&manifest).expect("Error reading manifest");
+        fs::write(
+            &manifest,
+            source.replace("# subfolders = ", "subfolders = "),
+        )
+        .expect("Error writing manifest");
+        validate::validate_stack(&stack_dir.to_string_lossy(), || unreachable!())
+            .expect("Uncommented subfolders example fails validation");
+
+        assert!(matches!(
+            new_stack("my-stack", templates_dir),
+            Err(Error::Config(_))
+        ));
+        assert!(matches!(
+            new_stack("../escape", templates_dir),
+            Err(Error::Config(_))
+        ));
+    }
+}
@@ -6,7 +6,13 @@ use std::{
 use handlebars::{Handlebars, RenderError};
 use serde::Serialize;
 
-pub fn render_to_file<T>(template: &str, data: &T, file: &mut File) -> Result<(), RenderError>
+use crate::error::{Context, Error, Result};
+
+pub fn render_to_file<T>(
+    template: &str,
+    data: &T,
+    file: &mut File,
+) -> std::result::Result<(), RenderError>
 where
     T: Serialize,
 {
@@ -15,30 +21,58 @@ where
     Ok(())
 }
 
+/// Renders a template without writing it anywhere, failing on variables `data` doesn't have
+pub fn check_template<T>(template: &str, data: &T) -> std::result::Result<(), RenderError>
+where
+    T: Serialize,
+{
+    let mut handlebars = Handlebars::new();
+    handlebars.set_strict_mode(true);
+    handlebars.render_template(template, data)?;
+    Ok(())
+}
+
 pub trait TemplateData {}
 
-pub fn generate_dir<T>(src: PathBuf, dest: PathBuf, data: &T, recursive: bool)
+/// Renders every template in `src` into `dest`, leaving any destination file listed in `keep`
+/// untouched
+pub fn generate_dir<T>(
+    src: PathBuf,
+    dest: PathBuf,
+    data: &T,
+    recursive: bool,
+    keep: &[PathBuf],
+) -> Result<()>
 where
     T: TemplateData + Serialize,
 {
-    fs::create_dir_all(&dest).expect("Failed to create directory");
-    for file in fs::read_dir(src).unwrap() {
-        if let Ok(file) = file {
-            if file.file_type().unwrap().is_dir() && recursive {
-                println!("Generating directory: {:?}", file.file_name());
-                let new_dest = dest.join(file.file_name().into_string().unwrap());
-                generate_dir(file.path(), new_dest, data, recursive);
-            } else {
-                println!("Generating file: {:?}", dest.join(file.file_name()));
-                let template = fs::read_to_string(file.path()).expect("Failed to read template");
-                let new_file = dest.join(file.file_name());
-                crate::template_generator::render_to_file(
-                    &template,
-                    data,
-                    &mut fs::File::create(new_file).unwrap(),
-                )
-                .expect((format!("Failed to render template: {:?}", &file)).as_str());
-            }
+    fs::create_dir_all(&dest)
+        .with_context(|| format!("Failed to create directory {}", dest.display()))?;
+    let entries = fs::read_dir(&src)
+        .with_context(|| format!("Failed to read template directory {}", src.display()))?;
+    for file in entries.flatten() {
+        let is_dir = file
+            .file_type()
+            .with_context(|| format!("Failed to read {}", file.path().display()))?
+            .is_dir();
+        if is_dir && recursive {
+            println!("Generating directory: {:?}", file.file_name());
+            let new_dest = dest.join(file.file_name());
+            generate_dir(file.path(), new_dest, data, recursive, keep)?;
+        } else if keep.contains(&dest.join(file.file_name())) {
+            println!("Keeping existing file: {:?}", dest.join(file.file_name()));
+        } else {
+            println!("Generating file: {:?}", dest.join(file.file_name()));
+            let template = fs::read_to_string(file.path())
+                .with_context(|| format!("Failed to read template {}", file.path().display()))?;
+            let new_file = dest.join(file.file_name());
+            let mut output = fs::File::create(&new_file)
+                .with_context(|| format!("Failed to create {}", new_file.display()))?;
+            render_to_file(&template, data, &mut output).map_err(|source| Error::Render {
+                template: file.path(),
+                source: Box::new(source),
+            })?;
         }
     }
+    Ok(())
 }
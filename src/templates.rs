@@ -0,0 +1,75 @@
+use std::{
+    env,
+    path::{Path, PathBuf},
+};
+
+use crate::error::{Context, Error, Result};
+
+pub const TEMPLATES_ENV: &str = "GERMINATE_TEMPLATES";
+
+/// Finds the templates directory, checking in order:
+/// 1. the `--templates-dir` flag
+/// 2. the `GERMINATE_TEMPLATES` environment variable
+/// 3. `$XDG_CONFIG_HOME/germinate/templates`, then `$XDG_DATA_HOME/germinate/templates`
+/// 4. a `templates` folder next to the germinate binary
+///
+/// An explicitly configured directory (flag or env var) that does not exist is an error rather
+/// than falling through to the next location.
+pub fn resolve_templates_dir(flag: Option<&Path>) -> Result<PathBuf> {
+    if let Some(dir) = flag {
+        return check_explicit(dir, "--templates-dir");
+    }
+
+    if let Some(dir) = env::var_os(TEMPLATES_ENV).filter(|dir| !dir.is_empty()) {
+        return check_explicit(Path::new(&dir), TEMPLATES_ENV);
+    }
+
+    let mut misses = vec![];
+    for (source, dir) in default_candidates() {
+        match dir {
+            Some(dir) if dir.is_dir() => return Ok(dir),
+            Some(dir) => misses.push(format!("  {}: {} does not exist", source, dir.display())),
+            None => misses.push(format!("  {}: could not be determined", source)),
+        }
+    }
+
+    Err(Error::Config(format!(
+        "No templates directory found, checked:\n{}\nPass --templates-dir <DIR> or set {}",
+        misses.join("\n"),
+        TEMPLATES_ENV
+    )))
+}
+
+fn check_explicit(dir: &Path, source: &str) -> Result<PathBuf> {
+    if dir.is_dir() {
+        // the builder changes into the project folder, so relative paths must be resolved now
+        dir.canonicalize()
+            .with_context(|| format!("Failed to resolve templates directory {}", dir.display()))
+    } else {
+        Err(Error::Config(format!(
+            "Templates directory from {} does not exist: {}",
+            source,
+            dir.display()
+        )))
+    }
+}
+
+fn default_candidates() -> Vec<(&'static str, Option<PathBuf>)> {
+    let home = env::var_os("HOME").map(PathBuf::from);
+    let xdg_dir = |var: &str, fallback: &str| {
+        env::var_os(var)
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| home.as_ref().map(|home| home.join(fallback)))
+            .map(|dir| dir.join("germinate").join("templates"))
+    };
+    let binary_dir = env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(|dir| dir.join("templates")));
+
+    vec![
+        ("XDG config dir", xdg_dir("XDG_CONFIG_HOME", ".config")),
+        ("XDG data dir", xdg_dir("XDG_DATA_HOME", ".local/share")),
+        ("binary directory", binary_dir),
+    ]
+}
@@ -4,31 +4,298 @@ use std::{
     path::{Path, PathBuf},
 };
 
+use crate::addons::Addon;
 use crate::config::PackageScripts;
+use crate::config::{Language, PackageManager};
+use crate::error::{Context, Error, Result};
+use crate::expression::Expr;
+use crate::linters::LinterSpec;
+use crate::manifest::{AddonEntry, DepTables, Group, Linters, Manifest, PromptEntry, ScriptTables};
 use crate::module::Module;
-use toml::{map::Map, Table, Value};
+use crate::prompts::{self, Prompt};
+use crate::schema::{Document, SchemaError};
+use crate::version;
+use indexmap::IndexMap;
+use schemars::JsonSchema;
+use serde::{de::DeserializeOwned, Deserialize};
+use toml::Value;
 
 type Dependencies = HashMap<String, Option<Vec<Module>>>;
 type Scripts = HashMap<String, PackageScripts>;
+/// Folder in the templates directory holding the dependency groups stacks can pull in
+pub const GROUPS_DIR: &str = "_groups";
+/// Result of the individual parsing steps, the message is attached to the template path by `new`
+type ParseResult<T> = std::result::Result<T, String>;
+
+/// A dependency's `when` condition, read before the manifests are merged so mistakes can be
+/// reported in the file they are in
+#[derive(Debug, Clone)]
+struct Condition {
+    location: SchemaError,
+    context: String,
+    source: String,
+}
+
+/// What `resolve` collects from every file it reads, to check it once the manifests are merged
+/// and report each mistake in the file it is in
+#[derive(Debug, Default)]
+struct Sources {
+    conditions: Vec<Condition>,
+    /// Where each prompt and linter table is, e.g. `prompts.auth`, in the stack furthest down
+    /// the `extends` chain that declares it
+    declarations: HashMap<String, SchemaError>,
+}
+
+impl Sources {
+    /// An error in the declaration of `key`, at the start of `path` if it has none
+    fn error(&self, path: &Path, key: &str, message: String) -> SchemaError {
+        match self.declarations.get(key) {
+            Some(location) => location.with_message(message),
+            None => SchemaError {
+                path: path.to_path_buf(),
+                line: 1,
+                column: 1,
+                message,
+            },
+        }
+    }
+}
+
+/// The deps of a manifest or group file, kept as values to check each of them on its own
+#[derive(Deserialize)]
+struct DepsFile {
+    #[serde(default)]
+    deps: IndexMap<String, Vec<Value>>,
+    #[serde(default)]
+    addons: IndexMap<String, AddonDeps>,
+}
+
+#[derive(Deserialize)]
+struct AddonDeps {
+    #[serde(default)]
+    deps: IndexMap<String, Vec<Value>>,
+}
+
 #[derive(Debug, Clone)]
 pub struct TomlTemplate {
+    title: Option<String>,
+    label: Option<String>,
+    description: Option<String>,
+    order: Option<i64>,
+    web: bool,
     subfolders: Option<Vec<PathBuf>>,
     scripts: Option<Scripts>,
     dependencies: Dependencies,
+    languages: Option<Vec<Language>>,
+    package_managers: Option<Vec<PackageManager>>,
+    prompts: Vec<Prompt>,
+    linters: Option<Vec<LinterSpec>>,
+    addons: Vec<Addon>,
+    /// Dependency groups the stack pulls in, including the ones of the stacks it extends
+    groups: Vec<String>,
+    /// Template folders the stack is built from, the extended stacks first
+    layers: Vec<PathBuf>,
 }
 
 impl TomlTemplate {
-    pub fn new(path: &Path) -> Self {
-        let table = Self::get_table(path);
-        let subfolders = Self::parse_subfolders(&table);
-        let scripts = Self::parse_scripts(&table);
-        let dependencies = Self::parse_deps(&table);
-
-        Self {
-            subfolders,
-            scripts,
-            dependencies,
+    /// Loads a manifest, merging in the manifests it `extends` (recursively). Every mistake in
+    /// the prompts, linters and dependency conditions is reported at once.
+    pub fn new(path: &Path) -> Result<Self> {
+        let mut chain = vec![];
+        let mut sources = Sources::default();
+        let (manifest, layers) = Self::resolve(path, &mut chain, &mut sources)?;
+        let mut errors = vec![];
+        let mut template = Self::from_manifest(manifest, path, &sources, &mut errors)
+            .map_err(|message| Error::template_parse(path, message))?;
+        for condition in &sources.conditions {
+            if let Err(message) =
+                prompts::parse_when(&condition.source, &condition.context, &template.prompts)
+            {
+                errors.push(condition.location.with_message(message));
+            }
+        }
+        if !errors.is_empty() {
+            errors.sort_by(|a, b| (&a.path, a.line, a.column).cmp(&(&b.path, b.line, b.column)));
+            return Err(Error::Schema {
+                path: path.to_path_buf(),
+                errors,
+            });
         }
+        template.layers = layers;
+        Ok(template)
+    }
+
+    /// Reads the manifest at `path` merged over the stack it extends. Also returns the template
+    /// folders of the chain, from the base stack to this one. `chain` holds the manifests being
+    /// resolved to detect cycles, `sources` collects what is checked after merging from every
+    /// file read.
+    fn resolve(
+        path: &Path,
+        chain: &mut Vec<PathBuf>,
+        sources: &mut Sources,
+    ) -> Result<(Manifest, Vec<PathBuf>)> {
+        let template_dir = path.parent().unwrap_or(Path::new("")).to_path_buf();
+        let mut manifest = Self::read_manifest::<Manifest>(path, sources)?;
+        let groups_dir = template_dir
+            .parent()
+            .unwrap_or(Path::new(""))
+            .join(GROUPS_DIR);
+        Self::expand_groups(&mut manifest, path, &groups_dir, sources)?;
+        let Some(parent) = manifest.extends.clone() else {
+            return Ok((manifest, vec![template_dir]));
+        };
+
+        chain.push(path.to_path_buf());
+        let parent_path = template_dir
+            .parent()
+            .unwrap_or(Path::new(""))
+            .join(&parent)
+            .join(path.file_name().unwrap_or_default());
+        if parent.contains(['/', '\\']) || !parent_path.is_file() {
+            return Err(Error::template_parse(
+                path,
+                format!("`extends` names unknown stack `{}`", parent),
+            ));
+        }
+        if chain.contains(&parent_path) {
+            let cycle = chain
+                .iter()
+                .chain([&parent_path])
+                .filter_map(|manifest| manifest.parent()?.file_name())
+                .map(|name| name.to_string_lossy())
+                .collect::<Vec<_>>();
+            return Err(Error::template_parse(
+                path,
+                format!("`extends` forms a cycle: {}", cycle.join(" -> ")),
+            ));
+        }
+
+        let (parent_manifest, mut layers) = Self::resolve(&parent_path, chain, sources)?;
+        let manifest = Manifest::merge(parent_manifest, manifest)
+            .map_err(|message| Error::template_parse(path, message))?;
+        layers.push(template_dir);
+        Ok((manifest, layers))
+    }
+
+    /// Replaces the manifest's deps with the deps of its `groups`, in the order they are listed,
+    /// with the manifest's own deps merged over them. A dependency listed by several groups or
+    /// by the manifest itself is only installed once, the last definition wins.
+    fn expand_groups(
+        manifest: &mut Manifest,
+        path: &Path,
+        groups_dir: &Path,
+        sources: &mut Sources,
+    ) -> Result<()> {
+        if manifest.groups.is_empty() {
+            return Ok(());
+        }
+        let mut deps = DepTables::default();
+        for name in &manifest.groups {
+            deps = deps.merge(Self::get_group(path, groups_dir, name, sources)?);
+        }
+        if let Some(own_deps) = manifest.deps.take() {
+            deps = deps.merge(own_deps);
+        }
+        manifest.deps = Some(deps);
+        Ok(())
+    }
+
+    /// Reads the deps of the group `name` for the manifest at `path`, checking them so mistakes
+    /// point at the group's file
+    fn get_group(
+        path: &Path,
+        groups_dir: &Path,
+        name: &str,
+        sources: &mut Sources,
+    ) -> Result<DepTables> {
+        let manifest_path = path;
+        let path = groups_dir.join(format!("{}.toml", name));
+        if name.starts_with('.') || name.contains(['/', '\\']) || !path.is_file() {
+            let mut available = fs::read_dir(groups_dir)
+                .into_iter()
+                .flatten()
+                .flatten()
+                .filter_map(|entry| {
+                    let path = entry.path();
+                    match path.extension() {
+                        Some(ext) if ext == "toml" => {
+                            Some(path.file_stem()?.to_string_lossy().to_string())
+                        }
+                        _ => None,
+                    }
+                })
+                .collect::<Vec<_>>();
+            available.sort();
+            let message = match available.is_empty() {
+                true => format!("`groups` names unknown group `{}`", name),
+                false => format!(
+                    "`groups` names unknown group `{}`, available groups: {}",
+                    name,
+                    available.join(", ")
+                ),
+            };
+            return Err(Error::template_parse(manifest_path, message));
+        }
+
+        let group = Self::read_manifest::<Group>(&path, sources)?;
+        Ok(group.deps)
+    }
+
+    /// Builds the stack from its merged manifest. Mistakes in its prompts and linters are added
+    /// to `errors`, located through `sources`, and leave them out of the stack.
+    fn from_manifest(
+        manifest: Manifest,
+        path: &Path,
+        sources: &Sources,
+        errors: &mut Vec<SchemaError>,
+    ) -> ParseResult<Self> {
+        let prompts = Self::parse_prompts(&manifest.prompts, path, sources, errors);
+        let deps = manifest
+            .deps
+            .ok_or("No deps keys found in TOML template file")?;
+        Ok(Self {
+            title: manifest.title,
+            label: manifest.label,
+            description: manifest.description,
+            order: manifest.order,
+            web: manifest.web.unwrap_or(false),
+            subfolders: manifest.subfolders.map(|subfolders| subfolders.paths()),
+            scripts: manifest.scripts.map(Self::format_scripts),
+            dependencies: Self::format_deps(deps),
+            languages: manifest.languages,
+            package_managers: manifest.package_managers,
+            linters: manifest
+                .linters
+                .map(|linters| Self::parse_linters(linters, &prompts, path, sources, errors)),
+            addons: manifest
+                .addons
+                .into_iter()
+                .map(|(name, addon)| Self::parse_addon(name, addon))
+                .collect(),
+            groups: manifest.groups,
+            prompts,
+            layers: vec![],
+        })
+    }
+
+    pub fn get_title(&self) -> Option<&str> {
+        self.title.as_deref()
+    }
+
+    pub fn get_label(&self) -> Option<&str> {
+        self.label.as_deref()
+    }
+
+    pub fn get_description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+
+    pub fn get_order(&self) -> Option<i64> {
+        self.order
+    }
+
+    pub fn is_web(&self) -> bool {
+        self.web
     }
 
     pub fn get_subfolders(&self) -> Option<&Vec<PathBuf>> {
@@ -43,189 +310,234 @@ impl TomlTemplate {
         &self.dependencies
     }
 
-    fn parse_deps(table: &Table) -> Dependencies {
-        let deps = match table.get("deps") {
-            Some(deps) => deps.as_table().expect("Error parsing dependencies"),
-            None => panic!("No deps keys found in TOML template file"),
-        };
+    pub fn get_prompts(&self) -> &[Prompt] {
+        &self.prompts
+    }
 
-        let package_managers = vec!["npm", "cargo"];
-        let parsed_deps = Self::fetch_deps(package_managers, deps);
+    pub fn get_languages(&self) -> Option<&[Language]> {
+        self.languages.as_deref()
+    }
 
-        parsed_deps
+    pub fn get_package_managers(&self) -> Option<&[PackageManager]> {
+        self.package_managers.as_deref()
     }
 
-    fn fetch_deps(keys: Vec<&str>, deps: &Table) -> Dependencies {
-        let mut results = HashMap::new();
-        keys.iter().for_each(|key| {
-            let packages = match deps.get(*key) {
-                Some(entries) => {
-                    let entries = entries
-                        .as_array()
-                        .expect(format!("Error retrieving {} dependencies", key).as_str());
-                    println!("Collecting {} dependencies", key);
-                    Some(Self::format_deps(entries))
-                }
-                None => None,
-            };
-            results.insert(key.to_string(), packages);
-        });
-        results
+    pub fn get_linters(&self) -> Option<&[LinterSpec]> {
+        self.linters.as_deref()
     }
 
-    fn format_deps(table: &Vec<Value>) -> Vec<Module> {
-        table
-            .iter()
-            .map(|dep| {
-                let dep = dep.as_table().expect("Error collecting deps");
-                let name = match dep.get("name") {
-                    Some(name) => name.as_str().expect("Error parsing name"),
-                    None => panic!("Error parsing name"),
-                };
-
-                //TODO: add semver crate to allow for parsing semver ranges
-                let version = match dep.get("version") {
-                    Some(version) => version.as_str().expect("Error parsing version"),
-                    None => "latest",
-                };
-
-                let dev = match dep.get("dev") {
-                    Some(dev) => dev.as_bool().expect("Error parsing dev"),
-                    None => false,
-                };
-
-                let then = match dep.get("then") {
-                    Some(cmds) => {
-                        let cmds = cmds
-                            .as_array()
-                            .expect("Error parsing then array")
-                            .iter()
-                            .map(|arr| arr.as_array().expect("Error parsing cmd"))
-                            .map(|cmd_arr| {
-                                cmd_arr
-                                    .iter()
-                                    .map(|arg| arg.as_str().expect("Error parsing arg").to_string())
-                                    .collect()
-                            })
-                            .collect();
-                        Some(cmds)
-                    }
-                    None => None,
-                };
-
-                let features = match dep.get("features") {
-                    Some(features) => Some(
-                        features
-                            .as_array()
-                            .expect("Error parsing dev")
-                            .iter()
-                            .map(|feature| {
-                                feature.as_str().expect("Error parsing feature").to_string()
-                            })
-                            .collect(),
-                    ),
-                    None => None,
-                };
-                Module::new(name.to_string(), version.to_string(), dev, then, features)
+    pub fn get_addons(&self) -> &[Addon] {
+        &self.addons
+    }
+
+    pub fn get_groups(&self) -> &[String] {
+        &self.groups
+    }
+
+    pub fn get_layers(&self) -> &[PathBuf] {
+        &self.layers
+    }
+
+    fn format_scripts(scripts: ScriptTables) -> Scripts {
+        [("npm", scripts.npm), ("cargo", scripts.cargo)]
+            .into_iter()
+            .filter_map(|(manager, scripts)| {
+                Some((manager.to_string(), scripts?.into_iter().collect()))
             })
             .collect()
     }
 
-    fn parse_scripts(table: &Map<String, Value>) -> Option<Scripts> {
-        let package_managers = vec!["npm", "cargo"];
-        match table.get("scripts") {
-            Some(scripts) => {
-                let scripts_table = scripts.as_table().expect("Error parsing dependencies");
-                Some(Self::format_scripts(package_managers, scripts_table))
+    fn parse_prompts(
+        entries: &IndexMap<String, PromptEntry>,
+        path: &Path,
+        sources: &Sources,
+        errors: &mut Vec<SchemaError>,
+    ) -> Vec<Prompt> {
+        let mut prompts: Vec<Prompt> = vec![];
+        for (name, entry) in entries {
+            match Prompt::parse(name, entry, &prompts) {
+                Ok(prompt) => prompts.push(prompt),
+                Err(message) => {
+                    errors.push(sources.error(path, &format!("prompts.{}", name), message))
+                }
             }
-            None => None,
+        }
+        prompts
+    }
+
+    /// Checks the variables of the linters' conditions against the stack's prompts
+    fn parse_linters(
+        linters: Linters,
+        prompts: &[Prompt],
+        path: &Path,
+        sources: &Sources,
+        errors: &mut Vec<SchemaError>,
+    ) -> Vec<LinterSpec> {
+        match linters {
+            Linters::Names(linters) => linters
+                .into_iter()
+                .map(|linter| LinterSpec { linter, when: None })
+                .collect(),
+            Linters::Conditional(linters) => linters
+                .into_iter()
+                .filter_map(|(linter, entry)| {
+                    let context = format!("linters.{}", linter.get_name());
+                    let when = entry
+                        .when
+                        .map(|when| prompts::parse_when(&when, &context, prompts))
+                        .transpose();
+                    match when {
+                        Ok(when) => Some(LinterSpec { linter, when }),
+                        Err(message) => {
+                            errors.push(sources.error(path, &context, message));
+                            None
+                        }
+                    }
+                })
+                .collect(),
         }
     }
 
-    fn format_scripts(keys: Vec<&str>, table: &Table) -> Scripts {
-        let mut results = HashMap::new();
-        keys.iter().for_each(|key| {
-            match table.get(*key) {
-                Some(entries) => {
-                    let mut scripts = HashMap::new();
-                    entries
-                        .as_table()
-                        .expect("Error extracting npm scripts table")
-                        .iter()
-                        .for_each(|(key, val)| {
-                            scripts.insert(
-                                key.to_string(),
-                                val.as_str().expect("Error parsing script").to_string(),
-                            );
-                        });
-                    results.insert(key.to_string(), scripts);
+    fn parse_addon(name: String, addon: AddonEntry) -> Addon {
+        let mut deps = Self::format_deps(addon.deps.unwrap_or_default());
+        Addon {
+            label: addon.label.unwrap_or_else(|| name.clone()),
+            description: addon.description.unwrap_or_default(),
+            npm_deps: deps.remove("npm").flatten().unwrap_or_default(),
+            cargo_deps: deps.remove("cargo").flatten().unwrap_or_default(),
+            scripts: addon.scripts.map(Self::format_scripts).unwrap_or_default(),
+            // defaults to addons/<name>, which may not exist for addons without files
+            files: addon
+                .files
+                .unwrap_or_else(|| Path::new("addons").join(&name)),
+            then: addon.then.unwrap_or_default(),
+            name,
+        }
+    }
+
+    /// Reads a manifest or group file as `T`, checking it against the schema of `T` and adding
+    /// its dependency conditions and where its prompts and linters are to `sources`
+    fn read_manifest<T: DeserializeOwned + JsonSchema>(
+        path: &Path,
+        sources: &mut Sources,
+    ) -> Result<T> {
+        let template_str = fs::read_to_string(path)
+            .with_context(|| format!("Error reading file: {}", path.display()))?;
+        let document = Document::parse(path, &template_str)
+            .map_err(|err| Error::template_parse(path, err.to_string()))?;
+        let mut errors = document.check::<T>();
+        let value = document.to_value();
+        for (context, dep) in Self::find_deps(&value) {
+            let when = dep.get("when").and_then(Value::as_str);
+            if let Some(when) = when {
+                let when_path = format!("{}.when", context);
+                match Expr::parse(when) {
+                    Ok(_) => sources.conditions.push(Condition {
+                        location: document.error(&when_path, String::new()),
+                        context: context.clone(),
+                        source: when.to_string(),
+                    }),
+                    Err(err) => errors.push(
+                        document.error(&when_path, format!("`{}` is invalid: {}", when_path, err)),
+                    ),
                 }
-                None => (),
-            };
-        });
+            }
 
-        results
-    }
-
-    fn parse_subfolders(table: &Map<String, Value>) -> Option<Vec<PathBuf>> {
-        match table.get("subfolders") {
-            Some(subfolders) => {
-                let subfolders = subfolders.as_table().expect("Error parsing subfolders");
-                let mut paths: Vec<PathBuf> = vec![];
-                let path = Path::new("");
-                let mut child_paths: Vec<PathBuf> = subfolders
-                    .iter()
-                    .flat_map(|child| Self::get_sub_paths(child, &path))
-                    .collect();
-                paths.append(&mut child_paths);
-                Some(paths)
+            let manager = context
+                .rsplit_once('[')
+                .and_then(|(key, _)| key.rsplit('.').next())
+                .and_then(PackageManager::from_name);
+            if let (Some(manager), Ok(module)) = (manager, dep.clone().try_into::<Module>()) {
+                if let Err(err) = module.check(&manager) {
+                    errors.push(document.error(&context, format!("`{}`: {}", context, err)));
+                }
             }
-            None => {
-                println!("No subfolders key in table");
-                None
+        }
+        if !errors.is_empty() {
+            errors.sort_by_key(|error| (error.line, error.column));
+            return Err(Error::Schema {
+                path: path.to_path_buf(),
+                errors,
+            });
+        }
+        for table in ["prompts", "linters"] {
+            let Some(Value::Table(entries)) = value.get(table) else {
+                continue;
+            };
+            for name in entries.keys() {
+                let key = format!("{}.{}", table, name);
+                // the extending stack is read first, so its declaration is kept
+                let location = document.error(&key, String::new());
+                sources.declarations.entry(key).or_insert(location);
             }
         }
+        value
+            .try_into::<T>()
+            .map_err(|err| Error::template_parse(path, err.message()))
     }
 
-    fn get_sub_paths((name, children): (&String, &toml::Value), path: &Path) -> Vec<PathBuf> {
-        let mut paths: Vec<PathBuf> = vec![];
-        let children = children
-            .as_table()
-            .expect("Error parsing subfolder children");
-        if children.is_empty() {
-            paths.push(path.join(name));
-        } else {
-            let path = path.join(name);
-            let mut child_paths: Vec<PathBuf> = children
-                .iter()
-                .flat_map(|child| Self::get_sub_paths(child, &path))
-                .collect();
-            paths.append(&mut child_paths);
+    /// Lists the file's deps and addon deps with their path, like `deps.cargo[1]`. A file whose
+    /// deps have the wrong shape yields none, the schema reports the shape instead.
+    fn find_deps(value: &Value) -> Vec<(String, Value)> {
+        let Ok(file) = value.clone().try_into::<DepsFile>() else {
+            return vec![];
+        };
+        let mut deps = vec![];
+        let addon_deps = file.addons.into_iter().flat_map(|(name, addon)| {
+            addon
+                .deps
+                .into_iter()
+                .map(move |(manager, deps)| (format!("addons.{}.deps.{}", name, manager), deps))
+        });
+        let file_deps = file
+            .deps
+            .into_iter()
+            .map(|(manager, deps)| (format!("deps.{}", manager), deps));
+        for (key, entries) in file_deps.chain(addon_deps) {
+            for (index, dep) in entries.into_iter().enumerate() {
+                deps.push((format!("{}[{}]", key, index), dep));
+            }
         }
+        deps
+    }
 
-        paths
+    /// Keys the deps by package manager, with `None` for the ones the manifest doesn't use, and
+    /// normalizes their versions
+    fn format_deps(deps: DepTables) -> Dependencies {
+        HashMap::from([
+            (
+                "npm".to_string(),
+                deps.npm
+                    .map(|deps| Self::normalize_versions(&PackageManager::Npm, deps)),
+            ),
+            (
+                "cargo".to_string(),
+                deps.cargo
+                    .map(|deps| Self::normalize_versions(&PackageManager::Cargo, deps)),
+            ),
+        ])
     }
 
-    //TODO? this requires templates folder to live in the same directory as the binary, could add a config/cli flag
-    fn get_table(path: &Path) -> Table {
-        let template_str =
-            fs::read_to_string(path).expect(&format!("Error reading file: {}", path.display()));
-        let table = template_str.parse::<Table>().expect("Error parsing toml");
-        table
+    /// Versions were checked when each file was read, so they all normalize
+    fn normalize_versions(manager: &PackageManager, mut deps: Vec<Module>) -> Vec<Module> {
+        for dep in &mut deps {
+            if let Ok(version) = version::normalize(manager, &dep.version) {
+                dep.version = version;
+            }
+        }
+        deps
     }
 }
 
 #[cfg(test)]
-
 pub mod tests {
-    use toml::map::Map;
-
     use super::*;
+    use crate::linters::Linter;
 
     #[test]
     fn test_parse_toml() {
         let path = Path::new("test/__mocks__/_test.toml");
-        let template = TomlTemplate::new(path);
+        let template = TomlTemplate::new(path).expect("Error parsing template");
 
         dbg!(&template);
         let deps_table = template.get_dependencies();
@@ -241,19 +553,243 @@ pub mod tests {
         assert!(!subfolders.is_empty());
     }
 
+    #[test]
+    fn parse_tooling() {
+        let manifest = toml::from_str::<Manifest>(
+            r#"
+            languages = ["typescript"]
+            package_managers = ["npm"]
+            deps = {}
+
+            [linters.eslint]
+            [linters.stylelint]
+            when = "spa or template_engine"
+            "#,
+        )
+        .unwrap();
+        let template =
+            TomlTemplate::from_manifest(manifest, Path::new(""), &Sources::default(), &mut vec![])
+                .expect("Error parsing tooling");
+
+        assert_eq!(template.get_languages(), Some(&[Language::TypeScript][..]));
+        assert_eq!(
+            template.get_package_managers(),
+            Some(&[PackageManager::Npm][..])
+        );
+        let linters = template.get_linters().expect("No linters extracted");
+        assert!(matches!(linters[0].linter, Linter::ESLint) && linters[0].when.is_none());
+        assert!(matches!(linters[1].linter, Linter::Stylelint) && linters[1].when.is_some());
+
+        let document = Document::parse(
+            Path::new(""),
+            "deps = {}\nlinters = [\"clippy\", \"rustfmt\"]",
+        )
+        .unwrap();
+        assert_eq!(
+            document.check::<Manifest>()[0].message,
+            "`linters[1]` must be one of eslint, stylelint, clippy, found `rustfmt`"
+        );
+    }
+
+    #[test]
+    fn merge_extended_template() {
+        let path = Path::new("test/__mocks__/extends/child/stack_template.toml");
+        let template = TomlTemplate::new(path).expect("Error parsing template");
+
+        assert_eq!(template.get_title(), Some("child"));
+        assert!(template.is_web());
+        assert_eq!(
+            template.get_layers(),
+            [
+                Path::new("test/__mocks__/extends/base"),
+                Path::new("test/__mocks__/extends/child")
+            ]
+        );
+
+        let deps = template.get_dependencies()["npm"].as_ref().unwrap();
+        let names = deps.iter().map(|dep| dep.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, ["hono", "typescript", "drizzle-orm"]);
+        assert_eq!(deps[0].version, "^4.0.0");
+
+        let scripts = &template.get_scripts().unwrap()["npm"];
+        let mut script_names = scripts.keys().collect::<Vec<_>>();
+        script_names.sort();
+        assert_eq!(script_names, ["start", "test"]);
+    }
+
+    #[test]
+    fn merge_dependency_groups() {
+        let path = Path::new("test/__mocks__/extends/grouped/stack_template.toml");
+        let template = TomlTemplate::new(path).expect("Error parsing template");
+        assert_eq!(template.get_groups(), ["validation", "web"]);
+
+        let deps = template.get_dependencies()["npm"].as_ref().unwrap();
+        let deps = deps
+            .iter()
+            .map(|dep| (dep.name.as_str(), dep.version.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(deps, [("zod", "^3.0.0"), ("hono", "^4.0.0")]);
+
+        let mut manifest = Manifest {
+            groups: vec!["validation".to_string(), "nope".to_string()],
+            ..Manifest::default()
+        };
+        let error = TomlTemplate::expand_groups(
+            &mut manifest,
+            path,
+            Path::new("test/__mocks__/extends/_groups"),
+            &mut Sources::default(),
+        )
+        .unwrap_err();
+        assert!(error
+            .to_string()
+            .ends_with("`groups` names unknown group `nope`, available groups: validation, web"));
+    }
+
+    #[test]
+    fn bundled_groups_keep_stack_deps() {
+        let cargo_deps = |stack: &str| {
+            let path = Path::new("templates")
+                .join(stack)
+                .join("stack_template.toml");
+            let template = TomlTemplate::new(&path).expect("Error parsing template");
+            let mut deps = template.get_dependencies()["cargo"]
+                .as_ref()
+                .unwrap()
+                .iter()
+                .filter(|dep| dep.when.is_none())
+                .map(|dep| format!("{} {:?}", dep.name, dep.features.as_deref().unwrap_or(&[])))
+                .collect::<Vec<_>>();
+            deps.sort();
+            deps
+        };
+        assert_eq!(
+            cargo_deps("rsapi"),
+            [
+                "axum [\"macros\"]",
+                "axum_test []",
+                "serde []",
+                "serde-json []",
+                "strum []",
+                "strum_macros []",
+                "time [\"macros\", \"serde\"]",
+                "tokio [\"macros\", \"rt-multi-thread\"]",
+                "tower []",
+                "validator [\"derive\"]",
+            ]
+        );
+        assert_eq!(
+            cargo_deps("rscli"),
+            [
+                "clap [\"derive\"]",
+                "console []",
+                "dialoguer []",
+                "indicatif []",
+                "ratatui [\"all-widgets\"]",
+                "serde []",
+                "strum [\"derive\", \"strum_macros\"]",
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_addons() {
+        let table = toml::toml! {
+            deps = {}
+
+            [addons.auth]
+            label = "Authentication"
+            files = "extras/auth"
+            then = [["bun", "run", "auth:init"]]
+
+            [[addons.auth.deps.npm]]
+            name = "lucia"
+
+            [addons.auth.scripts.npm]
+            "auth:init" = "bun run scripts/auth.ts"
+
+            [addons.openapi]
+        };
+        let manifest = Value::Table(table).try_into::<Manifest>().unwrap();
+        let template =
+            TomlTemplate::from_manifest(manifest, Path::new(""), &Sources::default(), &mut vec![])
+                .expect("Error parsing addons");
+        let addons = template.get_addons();
+
+        assert_eq!(addons[0].name, "auth");
+        assert_eq!(addons[0].label, "Authentication");
+        assert_eq!(addons[0].npm_deps[0].name, "lucia");
+        assert_eq!(addons[0].files, Path::new("extras/auth"));
+        assert_eq!(addons[0].then, [["bun", "run", "auth:init"]]);
+        assert!(addons[0].scripts["npm"].contains_key("auth:init"));
+        assert_eq!(addons[1].label, "openapi");
+        assert_eq!(addons[1].files, Path::new("addons/openapi"));
+    }
+
+    #[test]
+    fn parse_dependency_conditions() {
+        let path = Path::new("test/__mocks__/conditions/valid/stack_template.toml");
+        let template = TomlTemplate::new(path).expect("Error parsing template");
+        let deps = template.get_dependencies()["cargo"].as_ref().unwrap();
+        assert!(deps[0].when.is_none());
+        assert_eq!(deps[1].when, Some(Expr::parse("cookies").unwrap()));
+
+        let path = Path::new("test/__mocks__/conditions/invalid/stack_template.toml");
+        let error = TomlTemplate::new(path).unwrap_err().to_string();
+        assert_eq!(
+            error,
+            format!(
+                "Invalid template {0}, found 3 error(s):
+  {0}:8:8: `deps.cargo[1].when` uses `cookies`, which is not a built-in option or an earlier prompt
+  {0}:10:10: `prompts.db`: `db` is a built-in variable, pick another name
+  {0}:13:10: `linters.clippy.when` uses `lint`, which is not a built-in option or an earlier prompt",
+                path.display()
+            )
+        );
+
+        let path = Path::new("test/__mocks__/conditions/syntax.toml");
+        let error = TomlTemplate::read_manifest::<Manifest>(path, &mut Sources::default())
+            .unwrap_err()
+            .to_string();
+        assert!(
+            error.ends_with(":3:8: `deps.npm[0].when` is invalid: expected a name or value at the end of the expression\n  test/__mocks__/conditions/syntax.toml:7:8: `deps.npm[1].when` is invalid: expected a name or value at the end of the expression"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn check_dependency_versions() {
+        let path = Path::new("test/__mocks__/versions/ruby.toml");
+        let error = TomlTemplate::read_manifest::<Manifest>(path, &mut Sources::default())
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("found 2 error(s)"), "{}", error);
+        assert!(
+            error.contains(":5:1: `deps.npm[0]`: `~> 4.0` is not a valid npm version range"),
+            "{}",
+            error
+        );
+        assert!(
+            error.contains(":9:1: `deps.cargo[1]`: `~> 1.0`"),
+            "{}",
+            error
+        );
+
+        let deps = get_deps();
+        assert_eq!(deps["npm"].as_ref().unwrap()[1].version, "^1.0.0");
+    }
+
     #[test]
     fn test_parse_deps() {
-        let deps_table = get_deps_table();
-        let parsed_deps = TomlTemplate::fetch_deps(vec!["npm", "cargo"], &deps_table);
-        assert!(parsed_deps.contains_key("npm"));
-        assert!(parsed_deps.contains_key("cargo"));
+        let parsed_deps = get_deps();
+        assert!(parsed_deps["npm"].is_some());
+        assert!(parsed_deps["cargo"].is_some());
     }
 
     #[test]
     fn fetch_npm_deps() {
-        let deps_table = get_deps_table();
-        let parsed_deps = TomlTemplate::fetch_deps(vec!["npm"], &deps_table);
-        assert!(parsed_deps.contains_key("npm"));
+        let parsed_deps = get_deps();
 
         let npm_deps = &parsed_deps["npm"].as_ref().unwrap();
         assert!(npm_deps.iter().any(|dep| dep.name == "test_npm_dep_min"));
@@ -266,7 +802,7 @@ pub mod tests {
             .find(|dep| dep.name == "test_npm_dev_dep_full")
             .expect("Error finding dep");
         assert_eq!(full_dev_dep.version, "^1.0.0");
-        assert_eq!(full_dev_dep.dev, true);
+        assert!(full_dev_dep.dev);
 
         let then_cmds = full_dev_dep.then.as_ref().expect("Error getting then cmds");
         assert_eq!(then_cmds.len(), 2);
@@ -280,9 +816,7 @@ pub mod tests {
 
     #[test]
     fn fetch_cargo_deps() {
-        let deps_table = get_deps_table();
-        let parsed_deps = TomlTemplate::fetch_deps(vec!["cargo"], &deps_table);
-        assert!(parsed_deps.contains_key("cargo"));
+        let parsed_deps = get_deps();
 
         let cargo_deps = &parsed_deps["cargo"].as_ref().unwrap();
         assert!(cargo_deps
@@ -297,7 +831,7 @@ pub mod tests {
             .find(|dep| dep.name == "test_cargo_dev_dep_full")
             .expect("Error finding dep");
         assert_eq!(full_dev_dep.version, "^1.0.0");
-        assert_eq!(full_dev_dep.dev, true);
+        assert!(full_dev_dep.dev);
 
         let then_cmds = full_dev_dep.then.as_ref().expect("Error getting then cmds");
         assert_eq!(then_cmds.len(), 2);
@@ -312,8 +846,8 @@ pub mod tests {
     #[test]
     fn extract_npm_scripts() {
         let path = Path::new("test/__mocks__/_test.toml");
-        let table = TomlTemplate::get_table(path);
-        let parsed_scripts = TomlTemplate::parse_scripts(&table).expect("Error parsing deps");
+        let template = TomlTemplate::new(path).expect("Error parsing template");
+        let parsed_scripts = template.get_scripts().expect("No scripts extracted");
 
         assert!(parsed_scripts.contains_key("npm"));
         let npm_scripts = parsed_scripts
@@ -329,11 +863,8 @@ pub mod tests {
     #[test]
     fn extract_subfolders() {
         let path = Path::new("test/__mocks__/_test.toml");
-        let table = TomlTemplate::get_table(path);
-        let folder_tree = TomlTemplate::parse_subfolders(&table);
-
-        assert!(folder_tree.is_some());
-        let folder_tree = folder_tree.unwrap();
+        let template = TomlTemplate::new(path).expect("Error parsing template");
+        let folder_tree = template.get_subfolders().expect("No subfolders extracted");
         dbg!(&folder_tree);
 
         assert_eq!(folder_tree.len(), 9);
@@ -349,13 +880,9 @@ pub mod tests {
     }
 
     // Helpers
-    fn get_deps_table() -> Map<String, Value> {
+    fn get_deps() -> Dependencies {
         let path = Path::new("test/__mocks__/_test.toml");
-        let toml_table = TomlTemplate::get_table(path);
-        let deps_table = match toml_table.get("deps") {
-            Some(deps) => deps.as_table().expect("Error parsing dependencies"),
-            None => panic!("No deps keys found in TOML template file"),
-        };
-        deps_table.to_owned()
+        let template = TomlTemplate::new(path).expect("Error parsing template");
+        template.get_dependencies().to_owned()
     }
 }
@@ -0,0 +1,413 @@
+use std::{
+    env, fs, iter,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use crate::{
+    container::DockerVariables,
+    dialogue::Database,
+    error::{Error, Result},
+    expression::Variables,
+    file_system,
+    module::Module,
+    prompts::{self, Prompt, PromptKind},
+    stack::{self, Stack, MANIFEST},
+    template_generator,
+};
+use strum::VariantNames;
+use toml::Value;
+
+/// Checks a stack the way a build would use it, printing every problem found: the manifest,
+/// the docker templates rendered for every database and prompt choice, the template folders and
+/// the executables of `then` commands. `stack` is a stack folder, or the name of a stack in
+/// `templates_dir`.
+pub fn validate_stack(stack: &str, templates_dir: impl FnOnce() -> Result<PathBuf>) -> Result<()> {
+    let template_dir = match Path::new(stack).join(MANIFEST).is_file() {
+        true => PathBuf::from(stack),
+        false => stack::find(&templates_dir()?, stack)?.template_dir,
+    };
+    println!("Validating {}", template_dir.display());
+
+    let stack = match Stack::load(&template_dir) {
+        Ok(stack) => {
+            println!("  ok     {}", MANIFEST);
+            stack
+        }
+        Err(err) => {
+            println!("  error  {}", err);
+            return Err(failed(&template_dir, 1));
+        }
+    };
+
+    let mut problems = vec![];
+    problems.append(&mut check_docker_files(&stack));
+    problems.append(&mut check_folders(&stack));
+    problems.append(&mut check_commands(&stack));
+
+    if problems.is_empty() {
+        println!("No problems found");
+        return Ok(());
+    }
+    for problem in &problems {
+        println!("  error  {}", problem);
+    }
+    Err(failed(&template_dir, problems.len()))
+}
+
+fn failed(template_dir: &Path, problems: usize) -> Error {
+    Error::template_parse(
+        template_dir,
+        format!("validation found {} problem(s)", problems),
+    )
+}
+
+/// Most prompt answer combinations the docker templates are rendered with, past it each prompt's
+/// choices are tried in turn with the other prompts at their defaults
+const MAX_COMBINATIONS: usize = 256;
+
+/// Renders every docker template for each build that can give it different variables: each
+/// database choice combined with the prompt answers, see `prompt_choices`
+fn check_docker_files(stack: &Stack) -> Vec<String> {
+    let variants = docker_variants(stack);
+
+    let mut problems = vec![];
+    let files = file_system::list_layered_files(&stack.template_dirs, "docker");
+    for (file, src) in &files {
+        let path = Path::new("docker").join(file);
+        let template = match fs::read_to_string(src.join(file)) {
+            Ok(template) => template,
+            Err(err) => {
+                problems.push(format!("{}: {}", path.display(), err));
+                continue;
+            }
+        };
+        let mut failed = false;
+        for (db, answers) in &variants {
+            let variables = DockerVariables::new("validate-app", db, answers);
+            if let Err(err) = template_generator::check_template(&template, &variables) {
+                let db = db.as_ref().map_or("no".to_string(), Database::get_name);
+                let answers = match answers.is_empty() {
+                    true => String::new(),
+                    false => format!(" and answers {}", describe_answers(answers)),
+                };
+                problems.push(format!(
+                    "{} fails to render with {} database{}: {}",
+                    path.display(),
+                    db,
+                    answers,
+                    err
+                ));
+                failed = true;
+                break;
+            }
+        }
+        if !failed {
+            println!("  ok     {} ({} variants)", path.display(), variants.len());
+        }
+    }
+    problems
+}
+
+/// The database and prompt answers of every distinct build, found by answering the prompts the
+/// way a build would for each database, ORM, SPA and template engine choice. Containers are on
+/// and no addon is picked, as the docker templates only see the database and the answers.
+fn docker_variants(stack: &Stack) -> Vec<(Option<Database>, Variables)> {
+    let toggles = |varies: bool| match varies {
+        true => vec![false, true],
+        false => vec![false],
+    };
+    let dbs = iter::once(None).chain(
+        Database::VARIANTS
+            .iter()
+            .filter_map(|name| Database::from_str(name).ok())
+            .map(Some),
+    );
+    let supplied = supplied_answers(&stack.prompts);
+
+    let mut variants: Vec<(Option<Database>, Variables)> = vec![];
+    for db in dbs {
+        for orm in toggles(db.is_some()) {
+            for spa in toggles(stack.web) {
+                for template_engine in toggles(stack.web) {
+                    let mut builtins = Variables::from([
+                        ("stack".to_string(), Value::String(stack.name.clone())),
+                        (
+                            "name".to_string(),
+                            Value::String("validate-app".to_string()),
+                        ),
+                        ("orm".to_string(), Value::Boolean(orm)),
+                        ("spa".to_string(), Value::Boolean(spa)),
+                        (
+                            "template_engine".to_string(),
+                            Value::Boolean(template_engine),
+                        ),
+                        ("containers".to_string(), Value::Boolean(true)),
+                        ("addons".to_string(), Value::Array(vec![])),
+                    ]);
+                    if let Some(db) = &db {
+                        builtins.insert("db".to_string(), Value::String(db.get_name()));
+                    }
+                    for supplied in &supplied {
+                        let mut variables = builtins.clone();
+                        let Ok(answers) =
+                            prompts::answer(&stack.prompts, supplied, &mut variables, false)
+                        else {
+                            continue;
+                        };
+                        let variant = (db.clone(), answers);
+                        if !variants.contains(&variant) {
+                            variants.push(variant);
+                        }
+                    }
+                }
+            }
+        }
+    }
+    variants
+}
+
+/// The prompt answers to build with: every combination of the prompts' choices, or each prompt's
+/// choices in turn when there are more than `MAX_COMBINATIONS` combinations
+fn supplied_answers(prompts: &[Prompt]) -> Vec<Variables> {
+    let choices = prompts.iter().map(prompt_choices).collect::<Vec<_>>();
+    let combinations = choices
+        .iter()
+        .try_fold(1usize, |count, choices| count.checked_mul(choices.len()));
+    if combinations.is_none_or(|count| count > MAX_COMBINATIONS) {
+        println!(
+            "  note   more than {} prompt answer combinations, trying each prompt's choices with the other prompts at their defaults",
+            MAX_COMBINATIONS
+        );
+        return prompts
+            .iter()
+            .zip(&choices)
+            .flat_map(|(prompt, choices)| {
+                choices
+                    .iter()
+                    .map(|choice| Variables::from([(prompt.name.clone(), choice.clone())]))
+            })
+            .collect();
+    }
+
+    let mut combinations = vec![Variables::new()];
+    for (prompt, choices) in prompts.iter().zip(&choices) {
+        combinations = combinations
+            .into_iter()
+            .flat_map(|answers| {
+                choices.iter().map(move |choice| {
+                    let mut answers = answers.clone();
+                    answers.insert(prompt.name.clone(), choice.clone());
+                    answers
+                })
+            })
+            .collect();
+    }
+    combinations
+}
+
+/// The answers tried for a prompt: its default, both values of a bool, every option of a select,
+/// and none, each and all options of a multiselect. Strings and ints only take their default.
+fn prompt_choices(prompt: &Prompt) -> Vec<Value> {
+    let strings =
+        |options: &[String]| Value::Array(options.iter().cloned().map(Value::String).collect());
+    let others = match prompt.kind {
+        PromptKind::Bool => vec![Value::Boolean(false), Value::Boolean(true)],
+        PromptKind::Select => prompt.options.iter().cloned().map(Value::String).collect(),
+        PromptKind::MultiSelect => iter::once(strings(&[]))
+            .chain(prompt.options.chunks(1).map(strings))
+            .chain(iter::once(strings(&prompt.options)))
+            .collect(),
+        PromptKind::String | PromptKind::Int => vec![],
+    };
+
+    let mut choices = vec![prompt.default_value()];
+    for choice in others {
+        if !choices.contains(&choice) {
+            choices.push(choice);
+        }
+    }
+    choices
+}
+
+fn describe_answers(answers: &Variables) -> String {
+    answers
+        .iter()
+        .map(|(name, value)| format!("{} = {}", name, value))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// The folders copied into projects must be folders, and an addon's `files` folder must exist
+/// unless it is the default one
+fn check_folders(stack: &Stack) -> Vec<String> {
+    let mut problems = vec![];
+    for template_dir in &stack.template_dirs {
+        for folder in ["before_install", "after_install", "docker"] {
+            let path = template_dir.join(folder);
+            if path.exists() && !path.is_dir() {
+                problems.push(format!("{} must be a folder", path.display()));
+            } else if path.is_dir() && template_dir == &stack.template_dir {
+                println!("  ok     {}", folder);
+            }
+        }
+    }
+    for addon in &stack.addons {
+        let default = Path::new("addons").join(&addon.name);
+        let found = stack
+            .template_dirs
+            .iter()
+            .any(|dir| dir.join(&addon.files).is_dir());
+        if !found && addon.files != default {
+            problems.push(format!(
+                "addons.{}.files names {}, which is not a folder of the stack",
+                addon.name,
+                addon.files.display()
+            ));
+        }
+    }
+    problems
+}
+
+/// Every `then` command must name an executable on PATH
+fn check_commands(stack: &Stack) -> Vec<String> {
+    let template = &stack.template;
+    let mut commands: Vec<(String, Vec<String>)> = vec![];
+    let mut add_deps = |context: &str, deps: &[Module]| {
+        for dep in deps {
+            for command in dep.then.iter().flatten() {
+                commands.push((format!("{} `{}`", context, dep.name), command.clone()));
+            }
+        }
+    };
+    let dependencies = template.get_dependencies();
+    for manager in ["npm", "cargo"] {
+        if let Some(Some(deps)) = dependencies.get(manager) {
+            add_deps(&format!("deps.{}", manager), deps);
+        }
+    }
+    for addon in &stack.addons {
+        add_deps(&format!("addons.{}.deps.npm", addon.name), &addon.npm_deps);
+        add_deps(
+            &format!("addons.{}.deps.cargo", addon.name),
+            &addon.cargo_deps,
+        );
+    }
+    for addon in &stack.addons {
+        for command in &addon.then {
+            commands.push((format!("addons.{}", addon.name), command.clone()));
+        }
+    }
+
+    let mut problems = vec![];
+    for (context, command) in commands {
+        if find_executable(&command[0]).is_none() {
+            problems.push(format!(
+                "{}: `then` runs `{}`, which is not on PATH",
+                context, command[0]
+            ));
+        }
+    }
+    if problems.is_empty() {
+        println!("  ok     then commands");
+    }
+    problems
+}
+
+/// Finds `program` the way a shell would, in the folders of PATH unless it is a path itself
+pub fn find_executable(program: &str) -> Option<PathBuf> {
+    if program.contains(std::path::MAIN_SEPARATOR) || program.contains('/') {
+        let path = PathBuf::from(program);
+        return is_executable(&path).then_some(path);
+    }
+    let paths = env::var_os("PATH")?;
+    env::split_paths(&paths)
+        .map(|dir| dir.join(program))
+        .find(|path| is_executable(path))
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    fs::metadata(path)
+        .is_ok_and(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file() || path.with_extension("exe").is_file()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validate_bundled_stacks() {
+        let templates_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("templates");
+        let discovered = stack::discover(&templates_dir).expect("Error discovering stacks");
+        assert!(discovered.invalid.is_empty(), "{:?}", discovered.invalid);
+        for stack in discovered.stacks {
+            assert_eq!(
+                check_docker_files(&stack),
+                Vec::<String>::new(),
+                "{}",
+                stack.name
+            );
+            assert_eq!(
+                check_folders(&stack),
+                Vec::<String>::new(),
+                "{}",
+                stack.name
+            );
+        }
+    }
+
+    #[test]
+    fn render_docker_files_with_every_prompt_choice() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join(MANIFEST),
+            r#"
+                title = "choices"
+
+                [[deps.npm]]
+                name = "hono"
+
+                [prompts.cache]
+                type = "bool"
+
+                [prompts.extras]
+                type = "multiselect"
+                options = ["mail", "queue"]
+            "#,
+        )
+        .unwrap();
+        fs::create_dir(dir.path().join("docker")).unwrap();
+        fs::write(
+            dir.path().join("docker/compose.yml"),
+            "{{#if cache}}{{cache_port}}{{/if}}",
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("docker/extras.yml"),
+            "{{#each extras}}{{this}}{{/each}}",
+        )
+        .unwrap();
+
+        let stack = Stack::load(dir.path()).unwrap();
+        // 4 database choices, no extras, each extra and both, with and without a cache
+        assert_eq!(docker_variants(&stack).len(), 4 * 4 * 2);
+        let problems = check_docker_files(&stack);
+        assert_eq!(problems.len(), 1, "{:?}", problems);
+        assert!(problems[0].starts_with(
+            "docker/compose.yml fails to render with no database and answers cache = true"
+        ));
+    }
+
+    #[test]
+    fn find_executables_on_path() {
+        assert!(find_executable("sh").is_some());
+        assert!(find_executable("germinate-missing-command").is_none());
+    }
+}
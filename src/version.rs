@@ -0,0 +1,174 @@
+use semver::{Version, VersionReq};
+
+use crate::config::PackageManager;
+
+/// Version installed when a dep doesn't declare one
+pub const LATEST: &str = "latest";
+
+/// Operators npm accepts in front of a version, longest first
+const NPM_OPERATORS: [&str; 7] = [">=", "<=", ">", "<", "=", "~", "^"];
+
+/// Checks a dep's version against the syntax of its package manager and returns it normalized:
+/// a Cargo version requirement for `cargo add`, an npm range or dist-tag for `bun add`
+pub fn normalize(manager: &PackageManager, version: &str) -> Result<String, String> {
+    if version == LATEST {
+        return Ok(version.to_string());
+    }
+    if version.trim().is_empty() {
+        return Err(format!(
+            "version must not be empty, leave it out to install `{}`",
+            LATEST
+        ));
+    }
+    match manager {
+        PackageManager::Cargo => cargo_requirement(version),
+        PackageManager::Npm => npm_range(version),
+    }
+    .map_err(|reason| {
+        let syntax = match manager {
+            PackageManager::Cargo => "Cargo version requirement",
+            PackageManager::Npm => "npm version range",
+        };
+        format!("`{}` is not a valid {}: {}", version, syntax, reason)
+    })
+}
+
+fn cargo_requirement(version: &str) -> Result<String, String> {
+    if version.contains("~>") {
+        return Err(pessimistic_operator());
+    }
+    if version.contains("||") {
+        return Err("Cargo has no `||`, list one requirement per dep".to_string());
+    }
+    VersionReq::parse(version)
+        .map(|requirement| requirement.to_string())
+        .map_err(|err| err.to_string())
+}
+
+/// Validates the npm range grammar: comparator sets joined by `||`, each either a hyphen range
+/// (`1.0.0 - 2.0.0`) or comparators separated by spaces (`>=1.2 <2`)
+fn npm_range(version: &str) -> Result<String, String> {
+    if version.contains("~>") {
+        return Err(pessimistic_operator());
+    }
+    if version.contains(',') {
+        return Err("npm separates comparators with spaces, not commas".to_string());
+    }
+    if is_dist_tag(version) {
+        return Ok(version.to_string());
+    }
+
+    let mut sets = vec![];
+    for set in version.split("||") {
+        if let Some((from, to)) = set.split_once(" - ") {
+            let (from, to) = (from.trim(), to.trim());
+            check_partial(from)?;
+            check_partial(to)?;
+            sets.push(format!("{} - {}", from, to));
+            continue;
+        }
+
+        let mut comparators = vec![];
+        let mut tokens = set.split_whitespace();
+        while let Some(token) = tokens.next() {
+            let operator = NPM_OPERATORS
+                .into_iter()
+                .find(|operator| token.starts_with(operator))
+                .unwrap_or("");
+            // npm allows a space between the operator and the version, e.g. `>= 1.2.0`
+            let partial = match &token[operator.len()..] {
+                "" => tokens
+                    .next()
+                    .ok_or_else(|| format!("`{}` is missing a version", operator))?,
+                partial => partial,
+            };
+            check_partial(partial)?;
+            comparators.push(format!("{}{}", operator, partial));
+        }
+        if comparators.is_empty() {
+            return Err("`||` needs a range on both sides".to_string());
+        }
+        sets.push(comparators.join(" "));
+    }
+    Ok(sets.join(" || "))
+}
+
+/// A version where any part may be a wildcard, e.g. `1`, `1.x`, `1.2.*` or `1.2.3-beta.1`
+fn check_partial(partial: &str) -> Result<(), String> {
+    let partial = partial.strip_prefix('v').unwrap_or(partial);
+    let parts = partial.splitn(3, '.').collect::<Vec<_>>();
+    // only a full version can carry a prerelease or build, which semver checks
+    if parts.len() == 3 && Version::parse(partial).is_ok() {
+        return Ok(());
+    }
+    let valid = parts.iter().all(|part| {
+        matches!(*part, "x" | "X" | "*")
+            || (!part.is_empty() && part.chars().all(|c| c.is_ascii_digit()))
+    });
+    match valid {
+        true => Ok(()),
+        false => Err(format!("`{}` is not a version", partial)),
+    }
+}
+
+/// Tags such as `next` or `beta` name a published version instead of a range
+fn is_dist_tag(version: &str) -> bool {
+    version.starts_with(|c: char| c.is_ascii_alphabetic())
+        && !matches!(version, "x" | "X")
+        && !(version.starts_with('v') && version[1..].starts_with(|c: char| c.is_ascii_digit()))
+        && version
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+}
+
+fn pessimistic_operator() -> String {
+    "`~>` is RubyGems syntax, use `~` to allow patch updates or `^` for compatible updates"
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize_cargo_requirements() {
+        let cargo = |version| normalize(&PackageManager::Cargo, version);
+        assert_eq!(cargo("latest").unwrap(), "latest");
+        assert_eq!(cargo("1.2").unwrap(), "^1.2");
+        assert_eq!(cargo("~1.2.3").unwrap(), "~1.2.3");
+        assert_eq!(cargo(">= 1.0, < 2").unwrap(), ">=1.0, <2");
+        assert_eq!(cargo("1.*").unwrap(), "1.*");
+
+        assert!(cargo("~> 1.2").unwrap_err().contains("RubyGems"));
+        assert!(cargo("^1 || ^2").unwrap_err().contains("no `||`"));
+        assert!(cargo(">=1 <2").is_err());
+        assert!(cargo("one").is_err());
+        assert_eq!(
+            cargo("").unwrap_err(),
+            "version must not be empty, leave it out to install `latest`"
+        );
+    }
+
+    #[test]
+    fn normalize_npm_ranges() {
+        let npm = |version| normalize(&PackageManager::Npm, version);
+        assert_eq!(npm("^4.0.0").unwrap(), "^4.0.0");
+        assert_eq!(npm(">= 1.2.0  <2").unwrap(), ">=1.2.0 <2");
+        assert_eq!(
+            npm("1.x || >=2.5.0 || 5.0.0 - 7.2.3").unwrap(),
+            "1.x || >=2.5.0 || 5.0.0 - 7.2.3"
+        );
+        assert_eq!(npm("~1.2.3-beta.2").unwrap(), "~1.2.3-beta.2");
+        assert_eq!(npm("*").unwrap(), "*");
+        assert_eq!(npm("next").unwrap(), "next");
+
+        assert!(npm("~> 1.2").unwrap_err().contains("RubyGems"));
+        assert!(npm(">=1, <2").unwrap_err().contains("commas"));
+        assert!(npm("^1 ||").is_err());
+        assert!(npm("1.2.3.4").is_err());
+        assert!(npm(">=").is_err());
+        assert!(npm(" ")
+            .unwrap_err()
+            .starts_with("version must not be empty"));
+    }
+}